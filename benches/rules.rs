@@ -0,0 +1,67 @@
+//! Benchmarks of the rules engine, run with `cargo bench`.
+use bevy_chess::fen::{parse_fen, STARTING_FEN};
+use bevy_chess::pieces::{is_attacked, is_hanging, Piece, PieceColor};
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+
+/// Positions to measure, from the opening to the endgame.
+const POSITIONS: &[(&str, &str)] = &[
+    ("start", STARTING_FEN),
+    (
+        "italian",
+        "r1bqk2r/pppp1ppp/2n2n2/2b1p3/2B1P3/2N2N2/PPPP1PPP/R1BQK2R w KQkq - 6 5",
+    ),
+    (
+        "middlegame",
+        "r2q1rk1/pp2bppp/2n1pn2/3p4/2PP4/2N1PN2/PP2BPPP/R2Q1RK1 w - - 0 10",
+    ),
+    ("endgame", "8/5k2/3p4/1p1Pp2p/pP2Pp1P/P4P1K/8/8 b - - 99 50"),
+];
+
+/// Count the valid moves of the side to move, as the game does to highlight them.
+fn count_moves(pieces: &Vec<Piece>, turn: PieceColor) -> usize {
+    pieces
+        .iter()
+        .filter(|piece| piece.color == turn)
+        .map(|piece| {
+            (0..8)
+                .flat_map(|x| (0..8).map(move |y| (x, y)))
+                .filter(|&to| piece.is_move_valid(to, pieces))
+                .count()
+        })
+        .sum()
+}
+
+fn rules(c: &mut Criterion) {
+    for &(name, fen) in POSITIONS {
+        let position = parse_fen(fen).unwrap();
+        let pieces = position.pieces;
+        let turn = position.turn;
+
+        c.bench_function(&format!("legal moves {}", name), |b| {
+            b.iter(|| count_moves(black_box(&pieces), turn))
+        });
+        // Only occupied squares, as pawns do not attack empty ones
+        c.bench_function(&format!("attacked pieces {}", name), |b| {
+            b.iter(|| {
+                pieces
+                    .iter()
+                    .filter(|piece| piece.color == turn)
+                    .filter(|piece| {
+                        is_attacked((piece.x, piece.y), turn.opponent(), black_box(&pieces))
+                    })
+                    .count()
+            })
+        });
+        c.bench_function(&format!("hanging pieces {}", name), |b| {
+            b.iter(|| {
+                pieces
+                    .iter()
+                    .filter(|piece| piece.color == turn && is_hanging(piece, black_box(&pieces)))
+                    .count()
+            })
+        });
+    }
+}
+
+criterion_group!(benches, rules);
+criterion_main!(benches);
@@ -0,0 +1,37 @@
+//! Pasted text goes straight to the FEN parser: it must reject anything it
+//! cannot read with an error, and the rules must cope with any position it accepts.
+//!
+//! Run with `cargo fuzz run fen` from the repository root.
+#![no_main]
+use bevy_chess::fen::{parse_fen, to_fen};
+use bevy_chess::history::san;
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|text: &str| {
+    let position = match parse_fen(text) {
+        Ok(position) => position,
+        Err(_) => return,
+    };
+
+    // Writing the position back gives a FEN with the same pieces
+    let fen = to_fen(&position.pieces, position.turn, 1);
+    let reparsed = parse_fen(&fen).expect("written FEN does not parse");
+    assert_eq!(reparsed.pieces.len(), position.pieces.len());
+    assert!(reparsed.turn == position.turn);
+    for (a, b) in reparsed.pieces.iter().zip(&position.pieces) {
+        assert!(a.color == b.color && a.piece_type == b.piece_type);
+        assert_eq!((a.x, a.y), (b.x, b.y));
+    }
+
+    // Positions need not be legal, e.g. without kings or with pawns on the last rank
+    let pieces = &position.pieces;
+    for piece in pieces {
+        for x in 0..8 {
+            for y in 0..8 {
+                if piece.is_move_valid((x, y), pieces) {
+                    san(piece, (x, y), pieces);
+                }
+            }
+        }
+    }
+});
@@ -0,0 +1,61 @@
+use bevy::prelude::*;
+use bevy::render::mesh::Indices;
+use bevy::render::pipeline::PrimitiveTopology;
+
+/// Half width of the arrow shaft.
+const SHAFT_HALF_WIDTH: f32 = 0.08;
+/// Half width of the arrow head.
+const HEAD_HALF_WIDTH: f32 = 0.25;
+/// Length of the arrow head.
+const HEAD_LENGTH: f32 = 0.35;
+
+/// Flat arrow mesh lying on the XZ plane, from the origin along +X.
+pub fn arrow_mesh(length: f32) -> Mesh {
+    let shaft_length = (length - HEAD_LENGTH).max(0.0);
+    let positions = vec![
+        // Shaft
+        [0.0, 0.0, -SHAFT_HALF_WIDTH],
+        [0.0, 0.0, SHAFT_HALF_WIDTH],
+        [shaft_length, 0.0, SHAFT_HALF_WIDTH],
+        [shaft_length, 0.0, -SHAFT_HALF_WIDTH],
+        // Head
+        [shaft_length, 0.0, -HEAD_HALF_WIDTH],
+        [shaft_length, 0.0, HEAD_HALF_WIDTH],
+        [length, 0.0, 0.0],
+    ];
+    let normals = vec![[0.0, 1.0, 0.0]; positions.len()];
+    let uvs = vec![[0.0, 0.0]; positions.len()];
+
+    let mut mesh = Mesh::new(PrimitiveTopology::TriangleList);
+    mesh.set_indices(Some(Indices::U32(vec![0, 1, 2, 0, 2, 3, 4, 5, 6])));
+    mesh.set_attribute(Mesh::ATTRIBUTE_POSITION, positions);
+    mesh.set_attribute(Mesh::ATTRIBUTE_NORMAL, normals);
+    mesh.set_attribute(Mesh::ATTRIBUTE_UV_0, uvs);
+    mesh
+}
+
+/// Arrow from the center of a square to the center of another, slightly above the board.
+pub fn arrow_bundle(
+    meshes: &mut Assets<Mesh>,
+    material: Handle<StandardMaterial>,
+    from: (u8, u8),
+    to: (u8, u8),
+) -> PbrBundle {
+    let start = Vec3::new(from.0 as f32, 0.02, from.1 as f32);
+    let direction = Vec3::new(to.0 as f32, 0.02, to.1 as f32) - start;
+
+    PbrBundle {
+        mesh: meshes.add(arrow_mesh(direction.length())),
+        material,
+        transform: Transform {
+            translation: start,
+            rotation: Quat::from_rotation_y((-direction.z).atan2(direction.x)),
+            ..Default::default()
+        },
+        visible: Visible {
+            is_visible: true,
+            is_transparent: true,
+        },
+        ..Default::default()
+    }
+}
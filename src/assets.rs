@@ -0,0 +1,417 @@
+use bevy::asset::LoadState;
+use bevy::prelude::*;
+use bevy::render::mesh::Indices;
+use bevy::render::pipeline::PrimitiveTopology;
+
+use crate::config::GameConfig;
+use crate::error::{report_error, ChessError};
+use crate::kit::*;
+use crate::pieces::{PieceColor, PieceType};
+use crate::ui::UiAssets;
+
+use std::iter;
+
+/// State of the application.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum AppState {
+    /// Waiting for the piece kit, its meshes and fonts to be loaded
+    Loading,
+    /// Board and pieces are spawned
+    Playing,
+    /// Pieces are moving after a move, pushed over Playing to ignore input
+    Animating,
+}
+
+/// Number of assets loaded so far.
+#[derive(Default)]
+pub struct LoadingProgress {
+    pub loaded: usize,
+    pub total: usize,
+    /// Assets failed to load, the game stays on the loading screen
+    pub failed: bool,
+}
+
+impl LoadingProgress {
+    /// Loaded part of the assets, between 0 and 1.
+    pub fn fraction(&self) -> f32 {
+        if self.total == 0 {
+            return 0.0;
+        }
+        self.loaded as f32 / self.total as f32
+    }
+}
+
+/// Meshes of a piece model, and their transform relative to the square.
+#[derive(Default)]
+pub struct ModelMeshes {
+    pub transform: Transform,
+    pub meshes: Vec<Handle<Mesh>>,
+}
+
+impl ModelMeshes {
+    /// Load the meshes of a kit model.
+    fn load(asset_server: &AssetServer, kit: &PieceKit, model: &PieceModel) -> Self {
+        let (x, y, z) = model.offset;
+        let mut transform = Transform::from_translation(Vec3::new(x, y, z));
+        transform.apply_non_uniform_scale(Vec3::new(kit.scale, kit.scale, kit.scale));
+        Self {
+            transform,
+            meshes: model
+                .meshes
+                .iter()
+                .map(|label| asset_server.load(format!("{}#{}", kit.file, label).as_str()))
+                .collect(),
+        }
+    }
+}
+
+/// Squares of one color merged in a single mesh, centered on their coordinates
+/// as `Vec3::new(x, 0, y)`, e.g. to draw the board in two calls.
+pub fn board_mesh(light: bool, square_size: f32) -> Mesh {
+    let extent = square_size / 2.0;
+    let mut positions = Vec::new();
+    let mut indices = Vec::new();
+    for x in 0..8 {
+        for y in 0..8 {
+            // a1 is dark
+            if ((x + y) % 2 == 1) != light {
+                continue;
+            }
+            let (x, y) = (x as f32, y as f32);
+            let first = positions.len() as u32;
+            positions.push([x + extent, 0.0, y - extent]);
+            positions.push([x + extent, 0.0, y + extent]);
+            positions.push([x - extent, 0.0, y + extent]);
+            positions.push([x - extent, 0.0, y - extent]);
+            indices.extend([0, 2, 1, 0, 3, 2].iter().map(|i| first + i));
+        }
+    }
+    let normals = vec![[0.0f32, 1.0, 0.0]; positions.len()];
+    let uvs = vec![[0.0f32, 0.0]; positions.len()];
+
+    let mut mesh = Mesh::new(PrimitiveTopology::TriangleList);
+    mesh.set_indices(Some(Indices::U32(indices)));
+    mesh.set_attribute(Mesh::ATTRIBUTE_POSITION, positions);
+    mesh.set_attribute(Mesh::ATTRIBUTE_NORMAL, normals);
+    mesh.set_attribute(Mesh::ATTRIBUTE_UV_0, uvs);
+    mesh
+}
+
+/// Meshes and materials shared by the board, pieces and highlights.
+pub struct ChessAssets {
+    kit: Handle<PieceKit>,
+    /// Path of the loaded kit, to fall back to the default one if it is broken
+    kit_path: String,
+    king: ModelMeshes,
+    queen: ModelMeshes,
+    bishop: ModelMeshes,
+    knight: ModelMeshes,
+    rook: ModelMeshes,
+    pawn: ModelMeshes,
+    white_material: Handle<StandardMaterial>,
+    black_material: Handle<StandardMaterial>,
+    white_ghost_material: Handle<StandardMaterial>,
+    black_ghost_material: Handle<StandardMaterial>,
+    white_dimmed_material: Handle<StandardMaterial>,
+    black_dimmed_material: Handle<StandardMaterial>,
+    darkened_material: Handle<StandardMaterial>,
+    pub square_mesh: Handle<Mesh>,
+    /// Light squares of the board, merged in a single mesh
+    pub light_squares: Handle<Mesh>,
+    /// Dark squares of the board, merged in a single mesh
+    pub dark_squares: Handle<Mesh>,
+    pub white_square: Handle<StandardMaterial>,
+    pub black_square: Handle<StandardMaterial>,
+    pub hovered_square: Handle<StandardMaterial>,
+    pub selected_square: Handle<StandardMaterial>,
+    /// Arrow of a move waiting for confirmation
+    pub pending_arrow: Handle<StandardMaterial>,
+    /// Square of a hanging piece
+    pub threat_marker: Handle<StandardMaterial>,
+    /// Squares of the last move
+    pub last_move_marker: Handle<StandardMaterial>,
+    /// Square of a king in check
+    pub check_marker: Handle<StandardMaterial>,
+    /// Destination of the selected piece
+    pub legal_move_marker: Handle<StandardMaterial>,
+}
+
+impl FromWorld for ChessAssets {
+    fn from_world(world: &mut World) -> Self {
+        // A kit given to the game, e.g. with --kit, unless one is configured for embedding
+        let path = match world.get_resource::<KitConfig>() {
+            Some(kit_config) => kit_config.path.clone(),
+            None => world
+                .get_resource_or_insert_with(GameConfig::default)
+                .kit
+                .clone()
+                .unwrap_or_else(|| DEFAULT_KIT.to_string()),
+        };
+        // The asset server and storages are inserted by DefaultPlugins, which the game
+        // plugins must be added after
+        let kit = world
+            .get_resource::<AssetServer>()
+            .expect("DefaultPlugins must be added before the game plugins")
+            .load(path.as_str());
+
+        let mut meshes = world
+            .get_resource_mut::<Assets<Mesh>>()
+            .expect("DefaultPlugins must be added before the game plugins");
+        let square_mesh = meshes.add(Mesh::from(shape::Plane { size: 1. }));
+        let light_squares = meshes.add(board_mesh(true, 1.0));
+        let dark_squares = meshes.add(board_mesh(false, 1.0));
+
+        let mut materials = world
+            .get_resource_mut::<Assets<StandardMaterial>>()
+            .expect("DefaultPlugins must be added before the game plugins");
+
+        Self {
+            kit,
+            kit_path: path,
+            king: ModelMeshes::default(),
+            queen: ModelMeshes::default(),
+            bishop: ModelMeshes::default(),
+            knight: ModelMeshes::default(),
+            rook: ModelMeshes::default(),
+            pawn: ModelMeshes::default(),
+            white_material: materials.add(Color::rgb(1.0, 0.8, 0.8).into()),
+            black_material: materials.add(Color::rgb(0.0, 0.2, 0.2).into()),
+            white_ghost_material: materials.add(Color::rgba(1.0, 0.8, 0.8, 0.4).into()),
+            black_ghost_material: materials.add(Color::rgba(0.0, 0.2, 0.2, 0.4).into()),
+            white_dimmed_material: materials.add(Color::rgba(1.0, 0.8, 0.8, 0.15).into()),
+            black_dimmed_material: materials.add(Color::rgba(0.0, 0.2, 0.2, 0.15).into()),
+            darkened_material: materials.add(Color::rgb(0.2, 0.05, 0.05).into()),
+            square_mesh,
+            light_squares,
+            dark_squares,
+            white_square: materials.add(Color::rgb(1.0, 0.9, 0.9).into()),
+            black_square: materials.add(Color::rgb(0.0, 0.1, 0.1).into()),
+            hovered_square: materials.add(Color::rgb(0.8, 0.3, 0.3).into()),
+            selected_square: materials.add(Color::rgb(0.9, 0.1, 0.1).into()),
+            pending_arrow: materials.add(Color::rgba(0.1, 0.6, 0.1, 0.7).into()),
+            threat_marker: materials.add(Color::rgba(1.0, 0.5, 0.0, 0.6).into()),
+            last_move_marker: materials.add(Color::rgba(0.9, 0.8, 0.2, 0.4).into()),
+            check_marker: materials.add(Color::rgba(1.0, 0.1, 0.1, 0.6).into()),
+            legal_move_marker: materials.add(Color::rgba(0.1, 0.6, 0.1, 0.6).into()),
+        }
+    }
+}
+
+impl ChessAssets {
+    /// Meshes of a piece model.
+    pub fn model(&self, piece_type: PieceType) -> &ModelMeshes {
+        match piece_type {
+            PieceType::King => &self.king,
+            PieceType::Queen => &self.queen,
+            PieceType::Bishop => &self.bishop,
+            PieceType::Knight => &self.knight,
+            PieceType::Rook => &self.rook,
+            PieceType::Pawn => &self.pawn,
+        }
+    }
+
+    /// All piece meshes, to wait for them to be loaded.
+    fn piece_meshes(&self) -> impl Iterator<Item = &Handle<Mesh>> {
+        self.king
+            .meshes
+            .iter()
+            .chain(&self.queen.meshes)
+            .chain(&self.bishop.meshes)
+            .chain(&self.knight.meshes)
+            .chain(&self.rook.meshes)
+            .chain(&self.pawn.meshes)
+    }
+
+    /// Load the meshes of a piece kit.
+    fn load_kit(&mut self, asset_server: &AssetServer, kit: &PieceKit) {
+        self.king = ModelMeshes::load(asset_server, kit, &kit.king);
+        self.queen = ModelMeshes::load(asset_server, kit, &kit.queen);
+        self.bishop = ModelMeshes::load(asset_server, kit, &kit.bishop);
+        self.knight = ModelMeshes::load(asset_server, kit, &kit.knight);
+        self.rook = ModelMeshes::load(asset_server, kit, &kit.rook);
+        self.pawn = ModelMeshes::load(asset_server, kit, &kit.pawn);
+    }
+
+    /// Load the default kit instead of a broken one, returning false if the default kit is broken.
+    fn fall_back(&mut self, asset_server: &AssetServer) -> bool {
+        if self.kit_path == DEFAULT_KIT {
+            return false;
+        }
+        warn!(
+            "cannot load piece kit {}, using the default kit",
+            self.kit_path
+        );
+        self.kit = asset_server.load(DEFAULT_KIT);
+        self.kit_path = DEFAULT_KIT.to_string();
+        // Meshes are only known once the new kit has been read
+        self.king = ModelMeshes::default();
+        self.queen = ModelMeshes::default();
+        self.bishop = ModelMeshes::default();
+        self.knight = ModelMeshes::default();
+        self.rook = ModelMeshes::default();
+        self.pawn = ModelMeshes::default();
+        true
+    }
+
+    /// Material of pieces of the given color.
+    pub fn material(&self, color: PieceColor) -> Handle<StandardMaterial> {
+        match color {
+            PieceColor::White => self.white_material.clone(),
+            PieceColor::Black => self.black_material.clone(),
+        }
+    }
+
+    /// Translucent material of pieces of the given color.
+    pub fn ghost_material(&self, color: PieceColor) -> Handle<StandardMaterial> {
+        match color {
+            PieceColor::White => self.white_ghost_material.clone(),
+            PieceColor::Black => self.black_ghost_material.clone(),
+        }
+    }
+
+    /// Faded material of pieces left out by focus mode.
+    pub fn dimmed_material(&self, color: PieceColor) -> Handle<StandardMaterial> {
+        match color {
+            PieceColor::White => self.white_dimmed_material.clone(),
+            PieceColor::Black => self.black_dimmed_material.clone(),
+        }
+    }
+
+    /// Whether the material is one of the translucent piece materials.
+    pub fn is_translucent_material(&self, material: &Handle<StandardMaterial>) -> bool {
+        *material == self.white_ghost_material
+            || *material == self.black_ghost_material
+            || *material == self.white_dimmed_material
+            || *material == self.black_dimmed_material
+    }
+
+    /// Material of a piece about to be captured.
+    pub fn darkened_material(&self) -> Handle<StandardMaterial> {
+        self.darkened_material.clone()
+    }
+}
+
+/// Watch the asset folder to reload piece kits when they are edited.
+fn watch_assets(asset_server: Res<AssetServer>) {
+    if let Err(err) = asset_server.watch_for_changes() {
+        warn!("cannot watch assets for changes: {:?}", err);
+    }
+}
+
+/// Load the meshes of the piece kit once loaded, and again when it is modified.
+/// All meshes are requested at once, so the asset server reads them concurrently.
+fn load_kit(
+    time: Res<Time>,
+    asset_server: Res<AssetServer>,
+    kits: Res<Assets<PieceKit>>,
+    mut assets: ResMut<ChessAssets>,
+    mut kit_events: EventReader<AssetEvent<PieceKit>>,
+) {
+    for event in kit_events.iter() {
+        match event {
+            AssetEvent::Created { handle } | AssetEvent::Modified { handle }
+                if *handle == assets.kit =>
+            {
+                if let Some(kit) = kits.get(handle) {
+                    let span = info_span!("load_kit");
+                    let _enter = span.enter();
+                    debug!(
+                        "read piece kit {} after {:.0} ms",
+                        assets.kit_path,
+                        time.seconds_since_startup() * 1000.0
+                    );
+                    assets.load_kit(&asset_server, kit);
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+/// Track loaded meshes and fonts, and start playing once they are all ready.
+fn check_assets(
+    time: Res<Time>,
+    asset_server: Res<AssetServer>,
+    meshes: Res<Assets<Mesh>>,
+    mut assets: ResMut<ChessAssets>,
+    ui_assets: Res<UiAssets>,
+    mut progress: ResMut<LoadingProgress>,
+    mut state: ResMut<State<AppState>>,
+    mut missing_meshes: Local<bool>,
+) -> Result<(), ChessError> {
+    // The failure was reported once, nothing more can be loaded
+    if progress.failed {
+        return Ok(());
+    }
+
+    let handles = assets
+        .piece_meshes()
+        .map(|handle| handle.id)
+        .chain(iter::once(assets.kit.id))
+        .chain(iter::once(ui_assets.font.id));
+
+    let mut total = 0;
+    let mut loaded = 0;
+    let mut failed = false;
+    for handle in handles {
+        total += 1;
+        match asset_server.get_load_state(handle) {
+            LoadState::Loaded => loaded += 1,
+            LoadState::Failed => failed = true,
+            _ => {}
+        }
+    }
+    // Labels missing from the glTF file leave their mesh empty once the file is loaded.
+    // Loaded meshes only reach their storage on the next frame, so wait for it.
+    let missing = loaded == total
+        && assets
+            .piece_meshes()
+            .any(|handle| meshes.get(handle).is_none());
+    failed |= missing && *missing_meshes;
+    *missing_meshes = missing;
+    if failed {
+        if !assets.fall_back(&asset_server) {
+            progress.failed = true;
+            return Err(ChessError::Assets(assets.kit_path.clone()));
+        }
+        return Ok(());
+    }
+
+    if progress.loaded != loaded || progress.total != total {
+        progress.loaded = loaded;
+        progress.total = total;
+    }
+    // Meshes are only known once the kit has been read
+    if loaded == total && !missing && !assets.king.meshes.is_empty() {
+        info!(
+            "loaded {} game assets in {:.0} ms",
+            total,
+            time.seconds_since_startup() * 1000.0
+        );
+        state.set(AppState::Playing)?;
+    }
+    Ok(())
+}
+
+/// Assets Plugin
+pub struct AssetsPlugin;
+
+impl Plugin for AssetsPlugin {
+    fn build(&self, app: &mut AppBuilder) {
+        app.add_asset::<PieceKit>()
+            .init_asset_loader::<PieceKitLoader>()
+            .init_resource::<ChessAssets>()
+            .init_resource::<LoadingProgress>()
+            .add_state(AppState::Loading)
+            .add_startup_system(watch_assets.system())
+            .add_system(load_kit.system().label("load_kit"))
+            .add_system_set(
+                SystemSet::on_update(AppState::Loading).with_system(
+                    check_assets
+                        .system()
+                        .chain(report_error.system())
+                        .after("load_kit"),
+                ),
+            );
+    }
+}
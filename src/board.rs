@@ -1,11 +1,24 @@
-use bevy::app::AppExit;
 use bevy::prelude::*;
-use bevy_mod_picking::{MeshButtonMaterials, PickableBundle, PickingEvent, SelectionEvent};
+use bevy_mod_picking::{MeshButtonMaterials, PickingCamera, PickingEvent, SelectionEvent};
 
+use crate::arrow::*;
+use crate::assets::*;
+use crate::clock::ChessClock;
+use crate::config::{random_below, GameConfig, PlayerColor};
+use crate::error::{report_error, ChessError};
+use crate::fen::Position;
+use crate::highlight::{HighlightLayer, Highlights};
+use crate::history::{san, MoveHistory};
+use crate::locale::*;
 use crate::pieces::*;
+use crate::settings::{BoardGap, KeyBindings, Settings};
+use crate::status::StatusMessage;
 
 use std::fmt;
 
+/// Distance beyond the edge of the board where clicks still snap to the nearest square.
+const SNAP_TOLERANCE: f32 = 0.3;
+
 pub struct Square {
     pub x: u8,
     pub y: u8,
@@ -16,21 +29,54 @@ pub struct SelectedSquare {
     entity: Option<Entity>,
 }
 
+/// Square entry from the keyboard, e.g. "e2".
+#[derive(Default)]
+struct KeyboardSelection {
+    /// File typed, waiting for its rank
+    file: Option<u8>,
+    /// Square under the cursor moved with the cursor keys, once used
+    cursor: Option<(u8, u8)>,
+}
+
 #[derive(Default)]
 pub struct SelectedPiece {
     entity: Option<Entity>,
 }
 
-struct Taken;
+impl SelectedPiece {
+    /// Selected piece, if any.
+    pub fn entity(&self) -> Option<Entity> {
+        self.entity
+    }
+}
+
+/// Move waiting for confirmation, as the selected piece and its destination.
+#[derive(Default)]
+pub struct PendingMove(pub Option<(Entity, (u8, u8))>);
+
+/// Arrow showing the pending move.
+struct PendingArrow;
+
+/// Square under the mouse cursor.
+#[derive(Default)]
+pub struct HoveredSquare {
+    pub entity: Option<Entity>,
+}
+
+/// Translucent preview of the selected piece on the hovered square.
+struct Ghost;
+
+/// Piece shown darkened as it would be captured by the previewed move.
+struct Darkened(PieceColor);
+
+/// Marker of a piece taken during this frame, about to be removed.
+pub struct Taken;
 
 pub struct PlayerTurn(pub PieceColor);
 
 impl PlayerTurn {
     fn toggle(&mut self) {
-        self.0 = match self.0 {
-            PieceColor::White => PieceColor::Black,
-            PieceColor::Black => PieceColor::White,
-        }
+        self.0 = self.0.opponent();
     }
 }
 
@@ -42,86 +88,271 @@ impl Default for PlayerTurn {
 
 impl fmt::Display for PlayerTurn {
     fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
-        write!(
-            formatter,
-            "{}",
-            match self.0 {
-                PieceColor::White => "White",
-                PieceColor::Black => "Black",
-            }
-        )
+        write!(formatter, "{}", self.0)
     }
 }
 
+/// Event sent when a piece is moved.
+pub struct MoveEvent {
+    /// The moved piece, before the move
+    pub piece: Piece,
+    /// Destination square
+    pub to: (u8, u8),
+    /// Piece taken by the move
+    pub captured: Option<Piece>,
+    /// Standard algebraic notation of the move, e.g. "Nxf3+"
+    pub san: String,
+}
+
+/// Event sent when a king is taken or a player runs out of time, ending the game.
+pub struct GameOverEvent {
+    /// Side that took the king, or whose opponent ran out of time
+    pub winner: PieceColor,
+    /// Square of the losing king, if it is on the board
+    pub square: Option<(u8, u8)>,
+}
+
+/// Winner of the game once it is over. Moves are refused until a new position is loaded.
+#[derive(Default)]
+pub struct GameResult(pub Option<PieceColor>);
+
+/// Event sent when a move or a selection is refused, as illegal or out of turn.
+pub struct IllegalMoveEvent;
+
+/// Event requesting to move a piece to a square.
+/// The move is only played if valid for the player to move.
+pub struct MoveRequestEvent {
+    pub piece: Entity,
+    pub to: (u8, u8),
+}
+
+/// Algebraic name of a square, e.g. "e4".
+pub fn square_name((x, y): (u8, u8)) -> String {
+    format!("{}{}", (b'a' + y) as char, x + 1)
+}
+
+/// Event requesting to replace the game with a new position.
+pub struct LoadPositionEvent {
+    pub position: Position,
+}
+
 pub struct BoardPlugin;
 
 impl Plugin for BoardPlugin {
     fn build(&self, app: &mut AppBuilder) {
         app.init_resource::<SelectedSquare>()
             .init_resource::<SelectedPiece>()
+            .init_resource::<HoveredSquare>()
+            .init_resource::<GameConfig>()
+            .init_resource::<PlayerColor>()
             .init_resource::<PlayerTurn>()
-            .add_startup_system(create_board.system())
-            .add_system(select_squares.system().label("select_square"))
-            .add_system(
-                select_piece
-                    .system()
-                    .label("select_piece")
-                    .after("select_square"),
+            .init_resource::<PendingMove>()
+            .add_event::<MoveRequestEvent>()
+            .add_event::<IllegalMoveEvent>()
+            .init_resource::<GameResult>()
+            .add_event::<MoveEvent>()
+            .add_event::<GameOverEvent>()
+            .init_resource::<KeyboardSelection>()
+            .add_event::<LoadPositionEvent>()
+            .add_system_set(
+                SystemSet::on_enter(AppState::Playing)
+                    .with_system(create_board.system())
+                    .with_system(start_turn.system()),
             )
+            .add_system(load_position.system())
+            // Input is ignored while pieces are moving
+            .add_system_set(
+                SystemSet::on_update(AppState::Playing)
+                    .with_system(select_squares.system().label("select_square"))
+                    .with_system(
+                        click_board
+                            .system()
+                            .label("click_board")
+                            .after("select_square"),
+                    )
+                    .with_system(
+                        type_square
+                            .system()
+                            .label("type_square")
+                            .after("click_board"),
+                    )
+                    .with_system(
+                        move_square_cursor
+                            .system()
+                            .label("move_square_cursor")
+                            .after("type_square"),
+                    )
+                    .with_system(
+                        select_piece
+                            .system()
+                            .chain(report_error.system())
+                            .label("select_piece")
+                            .after("move_square_cursor"),
+                    )
+                    .with_system(
+                        move_piece
+                            .system()
+                            .chain(report_error.system())
+                            .label("move_piece")
+                            .after("select_piece"),
+                    )
+                    .with_system(
+                        confirm_move
+                            .system()
+                            .label("confirm_move")
+                            .after("move_piece"),
+                    )
+                    .with_system(auto_move.system().label("auto_move").after("confirm_move"))
+                    .with_system(play_move.system().label("play_move").after("auto_move")),
+            )
+            .add_system(hover_squares.system().label("hover_square"))
+            .add_system(resize_squares.system())
+            .add_system(show_pending_move.system().after("play_move"))
+            .add_system(show_threats.system().after("play_move"))
+            .add_system(highlight_squares.system().after("play_move"))
+            .add_system(show_square_cursor.system().after("play_move"))
+            .add_system(show_legal_moves.system().after("play_move"))
+            .add_system(show_last_move.system().after("play_move"))
+            .add_system(show_check.system().after("play_move"))
             .add_system(
-                move_piece
+                preview_move
                     .system()
-                    .label("move_piece")
-                    .after("select_piece"),
+                    .label("preview_move")
+                    .after("hover_square")
+                    .after("play_move"),
             )
+            .add_system(focus_pieces.system().after("preview_move"))
             .add_system(
                 remove_taken_pieces
                     .system()
                     .label("remove_taken_piece")
-                    .after("move_piece"),
+                    .after("play_move"),
             );
     }
 }
 
 fn create_board(
     mut commands: Commands,
-    mut meshes: ResMut<Assets<Mesh>>,
-    mut materials: ResMut<Assets<StandardMaterial>>,
+    assets: Res<ChessAssets>,
     mut button_materials: ResMut<MeshButtonMaterials>,
 ) {
-    let mesh = meshes.add(Mesh::from(shape::Plane { size: 1. }));
+    button_materials.hovered = assets.hovered_square.clone();
+    button_materials.selected = assets.selected_square.clone();
 
-    let white_material = materials.add(Color::rgb(1.0, 0.9, 0.9).into());
-    let black_material = materials.add(Color::rgb(0.0, 0.1, 0.1).into());
-
-    button_materials.hovered = materials.add(Color::rgb(0.8, 0.3, 0.3).into());
-    button_materials.selected = materials.add(Color::rgb(0.9, 0.1, 0.1).into());
+    // Squares of each color are drawn as a single mesh, clicks are found from the board plane
+    for &(mesh, material) in [
+        (&assets.light_squares, &assets.white_square),
+        (&assets.dark_squares, &assets.black_square),
+    ]
+    .iter()
+    {
+        commands.spawn_bundle(PbrBundle {
+            mesh: mesh.clone(),
+            material: material.clone(),
+            ..Default::default()
+        });
+    }
 
     // 64 squares
     for i in 0..8 {
         for j in 0..8 {
-            // Alternating square pattern
-            let square_material = if (i + j + 1) % 2 == 0 {
-                white_material.clone()
-            } else {
-                black_material.clone()
-            };
-            commands
-                .spawn_bundle(PbrBundle {
-                    mesh: mesh.clone(),
-                    material: square_material,
-                    transform: Transform::from_translation(Vec3::new(i as f32, 0.0, j as f32)),
-                    ..Default::default()
-                })
-                .insert_bundle(PickableBundle::default())
-                .insert(Square { x: i, y: j });
+            commands.spawn().insert(Square { x: i, y: j });
         }
     }
 }
 
+/// Rebuild the board meshes to leave the gap chosen in the settings
+fn resize_squares(
+    settings: Res<Settings>,
+    assets: Res<ChessAssets>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut applied: Local<Option<BoardGap>>,
+) {
+    if *applied == Some(settings.board_gap) {
+        return;
+    }
+    *applied = Some(settings.board_gap);
+
+    let size = settings.board_gap.square_size();
+    if let Some(mesh) = meshes.get_mut(&assets.light_squares) {
+        *mesh = board_mesh(true, size);
+    }
+    if let Some(mesh) = meshes.get_mut(&assets.dark_squares) {
+        *mesh = board_mesh(false, size);
+    }
+}
+
+/// Give the move to the side to move in the configured starting position,
+/// and number the moves from it.
+fn start_turn(
+    config: Res<GameConfig>,
+    mut turn: ResMut<PlayerTurn>,
+    mut history: ResMut<MoveHistory>,
+) {
+    let position = config.starting_position();
+    turn.0 = position.turn;
+    history.start_from(&position);
+}
+
+/// Square of the piece owning a picked mesh.
+fn picked_square(
+    entity: Entity,
+    index: &PieceIndex,
+    squares_query: &Query<(Entity, &Square)>,
+    parents_query: &Query<&Parent>,
+) -> Option<Entity> {
+    let piece = parents_query.get(entity).ok()?.0;
+    square_entity(squares_query, index.square_of(piece)?)
+}
+
+/// Entity of the square at the given coordinates.
+fn square_entity(squares_query: &Query<(Entity, &Square)>, (x, y): (u8, u8)) -> Option<Entity> {
+    squares_query
+        .iter()
+        .find(|(_, square)| square.x == x && square.y == y)
+        .map(|(entity, _)| entity)
+}
+
+/// Point of the board plane under the cursor.
+fn cursor_on_board(
+    windows: &Windows,
+    camera: &Camera,
+    camera_transform: &GlobalTransform,
+) -> Option<Vec3> {
+    let window = windows.get(camera.window)?;
+    let cursor = window.cursor_position()?;
+
+    // Cast a ray from the cursor onto the board plane
+    let ndc = cursor / Vec2::new(window.width(), window.height()) * 2.0 - Vec2::ONE;
+    let ndc_to_world = camera_transform.compute_matrix() * camera.projection_matrix.inverse();
+    let near = ndc_to_world.project_point3(ndc.extend(0.0));
+    let far = ndc_to_world.project_point3(ndc.extend(1.0));
+    let direction = far - near;
+    if direction.y.abs() < f32::EPSILON {
+        return None;
+    }
+    Some(near - direction * (near.y / direction.y))
+}
+
+/// Square nearest to a point of the board plane, if the point is on the board
+/// or at most `tolerance` beyond its edge.
+fn nearest_square(point: Vec3, tolerance: f32) -> Option<(u8, u8)> {
+    let min = -0.5 - tolerance;
+    let max = 7.5 + tolerance;
+    if point.x < min || point.x > max || point.z < min || point.z > max {
+        return None;
+    }
+    let x = point.x.round().max(0.0).min(7.0) as u8;
+    let y = point.z.round().max(0.0).min(7.0) as u8;
+    Some((x, y))
+}
+
 fn select_squares(
     mut selected_square: ResMut<SelectedSquare>,
     mut picking_event_reader: EventReader<PickingEvent>,
+    index: Res<PieceIndex>,
+    squares_query: Query<(Entity, &Square)>,
+    parents_query: Query<&Parent>,
 ) {
     // Interested only in selectino event
     let selection_events = picking_event_reader.iter().filter_map(|e| match e {
@@ -133,12 +364,14 @@ fn select_squares(
         match event {
             SelectionEvent::JustSelected(entity) => {
                 // Mark selected square
-                selected_square.entity.replace(*entity);
-                // println!("select square: {:?}", *entity);
+                if let Some(square) = picked_square(*entity, &index, &squares_query, &parents_query)
+                {
+                    selected_square.entity.replace(square);
+                }
             }
             SelectionEvent::JustDeselected(entity) => {
-                if Some(*entity) == selected_square.entity {
-                    // println!("deselect square: {:?}", *entity);
+                let square = picked_square(*entity, &index, &squares_query, &parents_query);
+                if square.is_some() && square == selected_square.entity {
                     selected_square.entity = None;
                 }
             }
@@ -146,187 +379,853 @@ fn select_squares(
     }
 }
 
+/// Select the clicked square of the board, pieces being picked from their meshes instead.
+/// Clicks missing the board by a small margin, e.g. at grazing camera angles, select the
+/// nearest square, and clicks further away drop the selection.
+fn click_board(
+    mut selected_square: ResMut<SelectedSquare>,
+    mouse_buttons: Res<Input<MouseButton>>,
+    windows: Res<Windows>,
+    camera_query: Query<(&Camera, &GlobalTransform, &PickingCamera)>,
+    squares_query: Query<(Entity, &Square)>,
+    ui_query: Query<&Interaction, With<Node>>,
+) {
+    if !mouse_buttons.just_pressed(MouseButton::Left) {
+        return;
+    }
+    // Clicks on the UI are not meant for the board
+    if ui_query.iter().any(|i| *i != Interaction::None) {
+        return;
+    }
+    let (camera, camera_transform, picking_camera) = match camera_query.iter().next() {
+        Some(camera) => camera,
+        None => return,
+    };
+    if picking_camera.intersect_top().is_some() {
+        return;
+    }
+
+    let square = cursor_on_board(&windows, camera, camera_transform)
+        .and_then(|point| nearest_square(point, SNAP_TOLERANCE))
+        .and_then(|square| square_entity(&squares_query, square));
+    if square.is_some() || selected_square.entity.is_some() {
+        selected_square.entity = square;
+    }
+}
+
+/// File of a key, from A to H.
+fn key_file(key: KeyCode) -> Option<u8> {
+    let file = match key {
+        KeyCode::A => 0,
+        KeyCode::B => 1,
+        KeyCode::C => 2,
+        KeyCode::D => 3,
+        KeyCode::E => 4,
+        KeyCode::F => 5,
+        KeyCode::G => 6,
+        KeyCode::H => 7,
+        _ => return None,
+    };
+    Some(file)
+}
+
+/// Rank of a key, from 1 to 8 on the main row or the numpad.
+fn key_rank(key: KeyCode) -> Option<u8> {
+    let rank = match key {
+        KeyCode::Key1 | KeyCode::Numpad1 => 0,
+        KeyCode::Key2 | KeyCode::Numpad2 => 1,
+        KeyCode::Key3 | KeyCode::Numpad3 => 2,
+        KeyCode::Key4 | KeyCode::Numpad4 => 3,
+        KeyCode::Key5 | KeyCode::Numpad5 => 4,
+        KeyCode::Key6 | KeyCode::Numpad6 => 5,
+        KeyCode::Key7 | KeyCode::Numpad7 => 6,
+        KeyCode::Key8 | KeyCode::Numpad8 => 7,
+        _ => return None,
+    };
+    Some(rank)
+}
+
+/// Select squares typed as a file then a rank, e.g. "e2" then "e4".
+/// Typed squares go through the same selection as clicked ones, so both can be mixed.
+fn type_square(
+    keys: Res<Input<KeyCode>>,
+    bindings: Res<KeyBindings>,
+    mut keyboard: ResMut<KeyboardSelection>,
+    mut selected_square: ResMut<SelectedSquare>,
+    squares_query: Query<(Entity, &Square)>,
+) {
+    // Modifiers are kept for shortcuts, e.g. Ctrl+V
+    let modifiers = [
+        KeyCode::LControl,
+        KeyCode::RControl,
+        KeyCode::LAlt,
+        KeyCode::RAlt,
+        KeyCode::LWin,
+        KeyCode::RWin,
+    ];
+    if modifiers.iter().any(|&key| keys.pressed(key)) {
+        return;
+    }
+
+    for &key in keys.get_just_pressed() {
+        if key == bindings.cancel {
+            keyboard.file = None;
+        } else if bindings.is_bound(key) {
+            // Keys of other actions, e.g. the cursor keys of the left hand profile
+            continue;
+        } else if let Some(file) = key_file(key) {
+            keyboard.file = Some(file);
+        } else if let (Some(file), Some(rank)) = (keyboard.file, key_rank(key)) {
+            keyboard.file = None;
+            if let Some((entity, _)) = squares_query
+                .iter()
+                .find(|(_, square)| square.x == rank && square.y == file)
+            {
+                selected_square.entity = Some(entity);
+            }
+        }
+    }
+}
+
+/// Move the square cursor with the cursor keys, relative to the side of the player,
+/// and select the square under it, for players without a mouse.
+fn move_square_cursor(
+    keys: Res<Input<KeyCode>>,
+    bindings: Res<KeyBindings>,
+    player: Res<PlayerColor>,
+    mut keyboard: ResMut<KeyboardSelection>,
+    mut selected_square: ResMut<SelectedSquare>,
+    squares_query: Query<(Entity, &Square)>,
+) {
+    // Towards the opponent, and to the right, from White's side
+    let mut step = (0, 0);
+    if keys.just_pressed(bindings.cursor_up) {
+        step.0 += 1;
+    }
+    if keys.just_pressed(bindings.cursor_down) {
+        step.0 -= 1;
+    }
+    if keys.just_pressed(bindings.cursor_right) {
+        step.1 += 1;
+    }
+    if keys.just_pressed(bindings.cursor_left) {
+        step.1 -= 1;
+    }
+    if player.0 == PieceColor::Black {
+        step = (-step.0, -step.1);
+    }
+
+    if step != (0, 0) {
+        // The cursor starts on the selected square, or the king's file of the player
+        let start = keyboard.cursor.or_else(|| {
+            selected_square
+                .entity
+                .and_then(|entity| squares_query.get(entity).ok())
+                .map(|(_, square)| (square.x, square.y))
+        });
+        let (x, y) = start.unwrap_or(match player.0 {
+            PieceColor::White => (0, 4),
+            PieceColor::Black => (7, 4),
+        });
+        keyboard.cursor = Some((
+            (x as i8 + step.0).clamp(0, 7) as u8,
+            (y as i8 + step.1).clamp(0, 7) as u8,
+        ));
+    }
+
+    if !keys.just_pressed(bindings.select) {
+        return;
+    }
+    if let Some((x, y)) = keyboard.cursor {
+        if let Some((entity, _)) = squares_query
+            .iter()
+            .find(|(_, square)| square.x == x && square.y == y)
+        {
+            selected_square.entity = Some(entity);
+        }
+    }
+}
+
+/// Highlight the square under the keyboard cursor, once the cursor keys were used.
+fn show_square_cursor(keyboard: Res<KeyboardSelection>, mut highlights: ResMut<Highlights>) {
+    if keyboard.is_changed() {
+        highlights.set(HighlightLayer::Cursor, keyboard.cursor);
+    }
+}
+
+/// Highlight the hovered and selected squares.
+fn highlight_squares(
+    hovered_square: Res<HoveredSquare>,
+    selected_square: Res<SelectedSquare>,
+    mut highlights: ResMut<Highlights>,
+    squares_query: Query<&Square>,
+) {
+    let coordinates = |entity: Option<Entity>| {
+        entity
+            .and_then(|entity| squares_query.get(entity).ok())
+            .map(|square| (square.x, square.y))
+    };
+    if hovered_square.is_changed() {
+        highlights.set(HighlightLayer::Hovered, coordinates(hovered_square.entity));
+    }
+    if selected_square.is_changed() {
+        highlights.set(
+            HighlightLayer::Selected,
+            coordinates(selected_square.entity),
+        );
+    }
+}
+
+/// Highlight the squares the selected piece can move to.
+fn show_legal_moves(
+    selected_piece: Res<SelectedPiece>,
+    board: Res<BoardState>,
+    mut highlights: ResMut<Highlights>,
+    pieces_query: Query<&Piece, Without<Taken>>,
+) {
+    if !selected_piece.is_changed() && !board.is_changed() {
+        return;
+    }
+
+    let pieces = &board.pieces;
+    let piece = selected_piece
+        .entity
+        .and_then(|entity| pieces_query.get(entity).ok());
+    highlights.set(
+        HighlightLayer::LegalMoves,
+        piece.into_iter().flat_map(|piece| {
+            (0..8)
+                .flat_map(|x| (0..8).map(move |y| (x, y)))
+                .filter(move |&to| piece.is_move_valid(to, pieces))
+        }),
+    );
+}
+
+/// Highlight the squares of the last move, until another position is loaded.
+fn show_last_move(
+    mut move_events: EventReader<MoveEvent>,
+    mut load_position_events: EventReader<LoadPositionEvent>,
+    mut highlights: ResMut<Highlights>,
+) {
+    if load_position_events.iter().last().is_some() {
+        highlights.clear(HighlightLayer::LastMove);
+    }
+    if let Some(event) = move_events.iter().last() {
+        let from = (event.piece.x, event.piece.y);
+        highlights.set(HighlightLayer::LastMove, [from, event.to].iter().copied());
+    }
+}
+
+/// Highlight the king of the player to move while it is attacked.
+fn show_check(turn: Res<PlayerTurn>, board: Res<BoardState>, mut highlights: ResMut<Highlights>) {
+    // The board state changes the frame after every move
+    if !board.is_changed() && !turn.is_changed() {
+        return;
+    }
+
+    let pieces = &board.pieces;
+    let checked = pieces
+        .iter()
+        .filter(|piece| piece.piece_type == PieceType::King && piece.color == turn.0)
+        .map(|king| (king.x, king.y))
+        .filter(|&square| is_attacked(square, turn.0.opponent(), pieces));
+    highlights.set(HighlightLayer::Check, checked);
+}
+
+/// Follow the square under the cursor: the square of a hovered piece, or of the board.
+fn hover_squares(
+    mut hovered_square: ResMut<HoveredSquare>,
+    windows: Res<Windows>,
+    index: Res<PieceIndex>,
+    camera_query: Query<(&Camera, &GlobalTransform, &PickingCamera)>,
+    squares_query: Query<(Entity, &Square)>,
+    parents_query: Query<&Parent>,
+) {
+    let (camera, camera_transform, picking_camera) = match camera_query.iter().next() {
+        Some(camera) => camera,
+        None => return,
+    };
+    let square = match picking_camera.intersect_top() {
+        Some((entity, _)) => picked_square(entity, &index, &squares_query, &parents_query),
+        None => cursor_on_board(&windows, camera, camera_transform)
+            .and_then(|point| nearest_square(point, 0.0))
+            .and_then(|square| square_entity(&squares_query, square)),
+    };
+    // Only changed when another square is hovered, for systems following it
+    if hovered_square.entity != square {
+        hovered_square.entity = square;
+    }
+}
+
 fn select_piece(
     selected_square: Res<SelectedSquare>,
     mut selected_piece: ResMut<SelectedPiece>,
     turn: Res<PlayerTurn>,
-    mut squares_query: Query<&Square>,
-    mut pieces_query: Query<(Entity, &mut Piece)>,
-) {
+    index: Res<PieceIndex>,
+    squares_query: Query<&Square>,
+    pieces_query: Query<&Piece>,
+) -> Result<(), ChessError> {
     if selected_square.is_changed() {
         if let Some(square_entity) = selected_square.entity {
-            let square = squares_query.get_mut(square_entity).unwrap();
+            let square = squares_query
+                .get(square_entity)
+                .map_err(|_| ChessError::MissingEntity("selected square"))?;
             if selected_piece.entity.is_none() {
-                for (piece_entity, piece) in pieces_query.iter_mut() {
-                    if piece.x == square.x && piece.y == square.y && piece.color == turn.0 {
-                        selected_piece.entity = Some(piece_entity);
-                        // println!("select piece: {:?}", piece_entity);
-                        break;
+                if let Some(piece_entity) = index.at((square.x, square.y)) {
+                    if let Ok(piece) = pieces_query.get(piece_entity) {
+                        if piece.color == turn.0 {
+                            selected_piece.entity = Some(piece_entity);
+                        }
                     }
                 }
             }
         } else {
-            // println!("deselect piece: {:?}", selected_piece.entity);
             selected_piece.entity.take();
         }
     }
+    Ok(())
 }
 
+/// Request to move the selected piece to the selected square.
 fn move_piece(
     mut commands: Commands,
-    mut selected_square: ResMut<SelectedSquare>,
+    selected_square: Res<SelectedSquare>,
     mut selected_piece: ResMut<SelectedPiece>,
-    mut turn: ResMut<PlayerTurn>,
-    mut squares_query: Query<&Square>,
-    mut pieces_query: Query<(Entity, &mut Piece)>,
-) {
+    settings: Res<Settings>,
+    index: Res<PieceIndex>,
+    board: Res<BoardState>,
+    mut pending_move: ResMut<PendingMove>,
+    mut move_request_events: EventWriter<MoveRequestEvent>,
+    mut illegal_move_events: EventWriter<IllegalMoveEvent>,
+    mut status_messages: EventWriter<StatusMessage>,
+    squares_query: Query<&Square>,
+    pieces_query: Query<&Piece>,
+) -> Result<(), ChessError> {
     if !selected_square.is_changed() {
-        return;
+        return Ok(());
     }
 
     let square_entity = match selected_square.entity {
         Some(entity) => entity,
-        None => return,
+        None => return Ok(()),
     };
 
     if selected_piece.is_changed() {
-        return;
+        return Ok(());
     }
 
     let piece_entity = match selected_piece.entity {
         Some(entity) => entity,
-        None => return,
+        None => return Ok(()),
     };
 
-    let square = squares_query.get_mut(square_entity).unwrap();
-    let pieces: Vec<Piece> = pieces_query.iter_mut().map(|(_, p)| *p).collect();
+    let square = squares_query
+        .get(square_entity)
+        .map_err(|_| ChessError::MissingEntity("selected square"))?;
+    let pieces = &board.pieces;
     // Find piece at the selected square
-    let other_entity = pieces_query
-        .iter_mut()
-        .filter_map(|(e, piece)| {
-            if piece.x == square.x && piece.y == square.y {
-                Some(e)
-            } else {
-                None
-            }
-        })
-        .next();
-    let (_, mut piece) = pieces_query.get_mut(piece_entity).unwrap();
+    let other_entity = index.at((square.x, square.y));
+    let piece = pieces_query
+        .get(piece_entity)
+        .map_err(|_| ChessError::MissingEntity("selected piece"))?;
+    let to = (square.x, square.y);
 
-    if piece.is_move_valid((square.x, square.y), &pieces) {
-        if other_entity.is_some() {
-            commands.entity(other_entity.unwrap()).insert(Taken);
+    if piece.is_move_valid(to, pieces) {
+        if settings.confirm_moves && pending_move.0 != Some((piece_entity, to)) {
+            // Wait for a second click or the confirm button
+            pending_move.0 = Some((piece_entity, to));
+        } else {
+            move_request_events.send(MoveRequestEvent {
+                piece: piece_entity,
+                to,
+            });
+        }
+    } else {
+        let own_piece = pieces
+            .iter()
+            .any(|p| p.x == square.x && p.y == square.y && p.color == piece.color);
+        if own_piece {
+            // Switch selection to the other piece of the same color
+            selected_piece.entity = other_entity;
+            pending_move.0 = None;
+        } else {
+            // Refuse the move, keeping the piece selected
+            commands.entity(piece_entity).insert(Shake::default());
+            illegal_move_events.send(IllegalMoveEvent);
+            status_messages.send(StatusMessage::new(tr(settings.language, "illegal_move")));
         }
+    }
+    Ok(())
+}
+
+/// Play the pending move when Enter is pressed.
+fn confirm_move(
+    keys: Res<Input<KeyCode>>,
+    bindings: Res<KeyBindings>,
+    pending_move: Res<PendingMove>,
+    mut move_request_events: EventWriter<MoveRequestEvent>,
+) {
+    if keys.just_pressed(bindings.confirm) {
+        if let Some((piece, to)) = pending_move.0 {
+            move_request_events.send(MoveRequestEvent { piece, to });
+        }
+    }
+}
+
+/// Play a random move for a player who exceeded the move time limit.
+fn auto_move(
+    config: Res<GameConfig>,
+    clock: Res<ChessClock>,
+    turn: Res<PlayerTurn>,
+    settings: Res<Settings>,
+    game_result: Res<GameResult>,
+    mut requested: Local<bool>,
+    mut move_request_events: EventWriter<MoveRequestEvent>,
+    mut status_messages: EventWriter<StatusMessage>,
+    board: Res<BoardState>,
+    pieces_query: Query<(Entity, &Piece), Without<Taken>>,
+) {
+    let limit = match config.move_time_limit {
+        Some(limit) => limit,
+        None => return,
+    };
+    // Request a single move, until the clock starts the next one
+    if clock.spent < limit {
+        *requested = false;
+        return;
+    }
+    if *requested || game_result.0.is_some() {
+        return;
+    }
+
+    let board = &board.pieces;
+    let pieces_query = &pieces_query;
+    let color = turn.0;
+    // Counted, then walked again up to the chosen one, instead of being collected
+    let moves = move || {
+        pieces_query
+            .iter()
+            .filter(move |(_, piece)| piece.color == color)
+            .flat_map(move |(entity, piece)| {
+                (0..8)
+                    .flat_map(|x| (0..8).map(move |y| (x, y)))
+                    .filter(move |&to| piece.is_move_valid(to, board))
+                    .map(move |to| (entity, to))
+            })
+    };
+    let count = moves().count();
+    let (piece, to) = match moves().nth(random_below(count)) {
+        Some(chosen) => chosen,
+        None => return,
+    };
+    *requested = true;
+    move_request_events.send(MoveRequestEvent { piece, to });
+    let color = color_name(settings.language, turn.0);
+    status_messages.send(StatusMessage(tr_with(
+        settings.language,
+        "auto_move",
+        color,
+    )));
+}
 
-        // Move selected piece
-        piece.x = square.x;
-        piece.y = square.y;
+/// Play requested moves that are valid for the player to move.
+fn play_move(
+    mut commands: Commands,
+    mut selected_square: ResMut<SelectedSquare>,
+    mut selected_piece: ResMut<SelectedPiece>,
+    mut pending_move: ResMut<PendingMove>,
+    mut turn: ResMut<PlayerTurn>,
+    mut index: ResMut<PieceIndex>,
+    mut move_request_events: EventReader<MoveRequestEvent>,
+    mut move_events: EventWriter<MoveEvent>,
+    mut illegal_move_events: EventWriter<IllegalMoveEvent>,
+    mut status_messages: EventWriter<StatusMessage>,
+    settings: Res<Settings>,
+    game_result: Res<GameResult>,
+    board: Res<BoardState>,
+    mut pieces_query: Query<&mut Piece>,
+) {
+    // Only one move can be played per turn
+    let request = match move_request_events.iter().next() {
+        Some(request) => request,
+        None => return,
+    };
+    if game_result.0.is_some() {
+        return;
+    }
+
+    // Pieces as they were before this move
+    let pieces = &board.pieces;
+    let mut piece = match pieces_query.get_mut(request.piece) {
+        Ok(piece) => piece,
+        Err(_) => return,
+    };
+    if piece.color != turn.0 {
+        illegal_move_events.send(IllegalMoveEvent);
+        let color = color_name(settings.language, turn.0);
+        status_messages.send(StatusMessage(tr_with(settings.language, "to_move", color)));
+        return;
+    }
+    if !piece.is_move_valid(request.to, pieces) {
+        illegal_move_events.send(IllegalMoveEvent);
+        status_messages.send(StatusMessage::new(tr(settings.language, "illegal_move")));
+        return;
+    }
 
-        // Update turn
-        turn.toggle();
+    let other_entity = index.at(request.to);
+    if let Some(other_entity) = other_entity {
+        commands.entity(other_entity).insert(Taken);
     }
 
-    // Clear selected square and selected piece
+    move_events.send(MoveEvent {
+        piece: *piece,
+        to: request.to,
+        captured: pieces.iter().find(|p| (p.x, p.y) == request.to).copied(),
+        san: san(&piece, request.to, pieces),
+    });
+
+    // Move selected piece
+    piece.x = request.to.0;
+    piece.y = request.to.1;
+    index.insert(request.piece, request.to);
+
+    // Update turn
+    turn.toggle();
+
+    // Clear selected square, selected piece and pending move
     selected_square.entity = None;
     selected_piece.entity = None;
-    // println!("deselect from move");
+    pending_move.0 = None;
 }
 
-fn remove_taken_pieces(
+/// Draw the pending move as an arrow, and drop it when the piece is deselected.
+fn show_pending_move(
     mut commands: Commands,
-    mut app_exit_events: EventWriter<AppExit>,
-    query: Query<(Entity, &Piece, &Taken)>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    assets: Res<ChessAssets>,
+    selected_piece: Res<SelectedPiece>,
+    index: Res<PieceIndex>,
+    mut pending_move: ResMut<PendingMove>,
+    mut highlights: ResMut<Highlights>,
+    arrow_query: Query<Entity, With<PendingArrow>>,
 ) {
-    for (entity, piece, _) in query.iter() {
-        // If king is taken, game is over
-        if piece.piece_type == PieceType::King {
-            app_exit_events.send(AppExit);
-        }
-        // Remove the piece
+    if selected_piece.entity.is_none() && pending_move.0.is_some() {
+        pending_move.0 = None;
+    }
+
+    if !pending_move.is_changed() {
+        return;
+    }
+
+    for entity in arrow_query.iter() {
         commands.entity(entity).despawn_recursive();
     }
+    highlights.clear(HighlightLayer::ArrowAnchors);
+
+    if let Some((piece, to)) = pending_move.0 {
+        if let Some(from) = index.square_of(piece) {
+            highlights.set(HighlightLayer::ArrowAnchors, [from, to].iter().copied());
+            commands
+                .spawn_bundle(arrow_bundle(
+                    &mut meshes,
+                    assets.pending_arrow.clone(),
+                    from,
+                    to,
+                ))
+                .insert(PendingArrow);
+        }
+    }
 }
 
-fn _select_squares(
+/// Highlight the hanging pieces of the player to move.
+fn show_threats(
+    settings: Res<Settings>,
+    turn: Res<PlayerTurn>,
+    board: Res<BoardState>,
+    mut highlights: ResMut<Highlights>,
+) {
+    // The board state changes the frame after every move
+    if !board.is_changed() && !settings.is_changed() {
+        return;
+    }
+
+    if !settings.show_threats {
+        highlights.clear(HighlightLayer::Threats);
+        return;
+    }
+
+    let pieces = &board.pieces;
+    let threatened = pieces
+        .iter()
+        .filter(|piece| piece.color == turn.0 && piece.piece_type != PieceType::King)
+        .filter(|piece| is_hanging(piece, pieces))
+        .map(|piece| (piece.x, piece.y));
+    highlights.set(HighlightLayer::Threats, threatened);
+}
+
+/// Show a ghost of the selected piece on the hovered square if the move is valid,
+/// and darken the piece it would capture.
+fn preview_move(
     mut commands: Commands,
-    mut selected_square: ResMut<SelectedSquare>,
-    mut selected_piece: ResMut<SelectedPiece>,
-    mut turn: ResMut<PlayerTurn>,
-    mut picking_event_reader: EventReader<PickingEvent>,
-    mut app_exit_events: EventWriter<AppExit>,
-    mut squares_query: Query<&Square>,
-    mut pieces_query: Query<(Entity, &mut Piece)>,
+    assets: Res<ChessAssets>,
+    selected_piece: Res<SelectedPiece>,
+    hovered_square: Res<HoveredSquare>,
+    index: Res<PieceIndex>,
+    board: Res<BoardState>,
+    squares_query: Query<&Square>,
+    pieces_query: Query<&Piece>,
+    ghost_query: Query<Entity, With<Ghost>>,
+    darkened_query: Query<(Entity, &Darkened)>,
+    children_query: Query<&Children>,
+    mut materials_query: Query<(&mut Handle<StandardMaterial>, &mut Visible)>,
 ) {
-    // Interested only in selectino event
-    let selection_events = picking_event_reader.iter().filter_map(|e| match e {
-        PickingEvent::Selection(selection) => Some(selection),
-        _ => None,
-    });
+    if !selected_piece.is_changed() && !hovered_square.is_changed() {
+        return;
+    }
 
-    let mut deselected = false;
-    let pieces: Vec<_> = pieces_query.iter_mut().map(|(_, p)| *p).collect();
+    // Clear the previous preview
+    for entity in ghost_query.iter() {
+        commands.entity(entity).despawn_recursive();
+    }
+    for (entity, darkened) in darkened_query.iter() {
+        set_children_material(
+            &assets,
+            entity,
+            assets.material(darkened.0),
+            &children_query,
+            &mut materials_query,
+        );
+        commands.entity(entity).remove::<Darkened>();
+    }
 
-    for event in selection_events {
-        match event {
-            SelectionEvent::JustSelected(entity) => {
-                let square_entity = *entity;
-                let square = squares_query.get_mut(square_entity).unwrap();
+    let (piece_entity, square_entity) = match (selected_piece.entity, hovered_square.entity) {
+        (Some(piece_entity), Some(square_entity)) => (piece_entity, square_entity),
+        _ => return,
+    };
+    let (piece, square) = match (
+        pieces_query.get(piece_entity),
+        squares_query.get(square_entity),
+    ) {
+        (Ok(piece), Ok(square)) => (piece, square),
+        _ => return,
+    };
 
-                // Mark selected square
-                deselected = false;
-                selected_square.entity = Some(square_entity);
-                if let Some(peice_entity) = selected_piece.entity {
-                    // Find piece at the selected square
-                    let other = pieces_query
-                        .iter_mut()
-                        .filter_map(|(e, piece)| {
-                            if piece.x == square.x && piece.y == square.y {
-                                Some((e, *piece))
-                            } else {
-                                None
-                            }
-                        })
-                        .next();
-                    // Move the selected piece to the selected square
-                    let (_, mut piece) = pieces_query.get_mut(peice_entity).unwrap();
-                    if piece.is_move_valid((square.x, square.y), &pieces) {
-                        // Remove the piece at the selected square
-                        if other.is_some() {
-                            let (other_entity, other_piece) = other.unwrap();
-                            // If king is taken, game is over
-                            if other_piece.piece_type == PieceType::King {
-                                app_exit_events.send(AppExit);
-                            }
-                            commands.entity(other_entity).despawn_recursive();
-                        }
-                        // Move selected piece
-                        piece.x = square.x;
-                        piece.y = square.y;
-                        // Update turn
-                        turn.toggle();
-                    }
-                    // Clear selected square and selected piece
-                    selected_square.entity = None;
-                    selected_piece.entity = None;
-                } else {
-                    // Select the piece in the currently selected square
-                    for (piece_entity, piece) in pieces_query.iter_mut() {
-                        if piece.x == square.x && piece.y == square.y && piece.color == turn.0 {
-                            selected_piece.entity = Some(piece_entity);
-                            break;
-                        }
-                    }
+    if !piece.is_move_valid((square.x, square.y), &board.pieces) {
+        return;
+    }
+
+    let ghost = spawn_model(
+        &mut commands,
+        &assets,
+        piece.piece_type,
+        assets.ghost_material(piece.color),
+        (square.x, square.y),
+    );
+    commands.entity(ghost).insert(Ghost);
+
+    if let Some(victim_entity) = index.at((square.x, square.y)) {
+        if let Ok(victim) = pieces_query.get(victim_entity) {
+            set_children_material(
+                &assets,
+                victim_entity,
+                assets.darkened_material(),
+                &children_query,
+                &mut materials_query,
+            );
+            commands
+                .entity(victim_entity)
+                .insert(Darkened(victim.color));
+        }
+    }
+}
+
+/// Set the material of all meshes of a piece model.
+fn set_children_material(
+    assets: &ChessAssets,
+    entity: Entity,
+    material: Handle<StandardMaterial>,
+    children_query: &Query<&Children>,
+    materials_query: &mut Query<(&mut Handle<StandardMaterial>, &mut Visible)>,
+) {
+    let is_transparent = assets.is_translucent_material(&material);
+    if let Ok(children) = children_query.get(entity) {
+        for child in children.iter() {
+            if let Ok((mut child_material, mut visible)) = materials_query.get_mut(*child) {
+                // Avoid touching meshes already set
+                if *child_material != material {
+                    *child_material = material.clone();
+                }
+                if visible.is_transparent != is_transparent {
+                    visible.is_transparent = is_transparent;
                 }
             }
-            SelectionEvent::JustDeselected(entity) => {
-                deselected = Some(*entity) == selected_square.entity;
-            }
+        }
+    }
+}
+
+/// Check if focus mode still shows a piece while another is selected: the selected piece,
+/// the pieces it can take, and the attackers of the king of the side to move.
+fn is_focused(piece: &Piece, selected: &Piece, turn: PieceColor, pieces: &Vec<Piece>) -> bool {
+    let square = (piece.x, piece.y);
+    let king = pieces
+        .iter()
+        .find(|king| king.piece_type == PieceType::King && king.color == turn);
+    square == (selected.x, selected.y)
+        || selected.is_move_valid(square, pieces)
+        || king.map_or(false, |king| {
+            piece.color != turn && piece.is_move_valid((king.x, king.y), pieces)
+        })
+}
+
+/// Fade the pieces that do not matter for the selected piece, in focus mode
+fn focus_pieces(
+    assets: Res<ChessAssets>,
+    settings: Res<Settings>,
+    turn: Res<PlayerTurn>,
+    selected_piece: Res<SelectedPiece>,
+    hovered_square: Res<HoveredSquare>,
+    index: Res<PieceIndex>,
+    board: Res<BoardState>,
+    squares_query: Query<&Square>,
+    pieces_query: Query<(Entity, &Piece), Without<Taken>>,
+    children_query: Query<&Children>,
+    mut materials_query: Query<(&mut Handle<StandardMaterial>, &mut Visible)>,
+) {
+    // The move preview restores materials when the hovered square changes
+    if !settings.is_changed()
+        && !selected_piece.is_changed()
+        && !hovered_square.is_changed()
+        && !board.is_changed()
+    {
+        return;
+    }
+
+    let pieces = &board.pieces;
+    let selected = selected_piece
+        .entity
+        .and_then(|entity| pieces_query.get(entity).ok())
+        .map(|(_, piece)| piece);
+    let focus = selected.filter(|_| settings.focus_mode);
+    // The piece the preview darkens, as it would be taken
+    let victim = match (selected, hovered_square.entity) {
+        (Some(selected), Some(square_entity)) => squares_query
+            .get(square_entity)
+            .ok()
+            .filter(|square| selected.is_move_valid((square.x, square.y), pieces))
+            .and_then(|square| index.at((square.x, square.y))),
+        _ => None,
+    };
+
+    for (entity, piece) in pieces_query.iter() {
+        if Some(entity) == victim {
+            continue;
+        }
+        let dimmed = focus.map_or(false, |selected| {
+            !is_focused(piece, selected, turn.0, pieces)
+        });
+        let material = if dimmed {
+            assets.dimmed_material(piece.color)
+        } else {
+            assets.material(piece.color)
         };
+        set_children_material(
+            &assets,
+            entity,
+            material,
+            &children_query,
+            &mut materials_query,
+        );
     }
+}
 
-    if deselected {
-        // Deselection
+/// Send taken pieces to the graveyard of their color.
+fn remove_taken_pieces(
+    mut commands: Commands,
+    mut game_result: ResMut<GameResult>,
+    mut game_over_events: EventWriter<GameOverEvent>,
+    query: Query<(Entity, &Piece, &Taken)>,
+    captured_query: Query<&Captured>,
+) {
+    for (entity, piece, _) in query.iter() {
+        // If king is taken, game is over
+        if piece.piece_type == PieceType::King {
+            let winner = piece.color.opponent();
+            game_result.0 = Some(winner);
+            game_over_events.send(GameOverEvent {
+                winner,
+                square: Some((piece.x, piece.y)),
+            });
+            commands.entity(entity).insert(Topple::default());
+        }
+        // Next free slot in the graveyard
+        let slot = captured_query
+            .iter()
+            .filter(|captured| captured.piece.color == piece.color)
+            .count();
+        // Remove the piece from the game
+        commands
+            .entity(entity)
+            .remove::<Piece>()
+            .remove::<Taken>()
+            .insert(Captured {
+                piece: *piece,
+                slot,
+            });
+    }
+}
+
+/// Reset turn and selection when a new position is loaded.
+fn load_position(
+    mut selected_square: ResMut<SelectedSquare>,
+    mut selected_piece: ResMut<SelectedPiece>,
+    mut turn: ResMut<PlayerTurn>,
+    mut game_result: ResMut<GameResult>,
+    mut load_position_events: EventReader<LoadPositionEvent>,
+) {
+    if let Some(event) = load_position_events.iter().last() {
+        turn.0 = event.position.turn;
+        game_result.0 = None;
         selected_square.entity = None;
         selected_piece.entity = None;
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::settings::KeyProfile;
+
+    #[test]
+    fn key_profiles_leave_squares_typable() {
+        let profiles = [
+            KeyProfile::Standard,
+            KeyProfile::LeftHand,
+            KeyProfile::RightHand,
+        ];
+        let keys = [
+            KeyCode::A,
+            KeyCode::B,
+            KeyCode::C,
+            KeyCode::D,
+            KeyCode::E,
+            KeyCode::F,
+            KeyCode::G,
+            KeyCode::H,
+            KeyCode::Key1,
+            KeyCode::Key2,
+            KeyCode::Key3,
+            KeyCode::Key4,
+            KeyCode::Key5,
+            KeyCode::Key6,
+            KeyCode::Key7,
+            KeyCode::Key8,
+        ];
+        for &profile in profiles.iter() {
+            let bindings = profile.bindings();
+            for &key in keys.iter() {
+                assert!(key_file(key).is_some() || key_rank(key).is_some());
+                assert!(!bindings.is_bound(key), "{:?} is bound", key);
+            }
+        }
+    }
+}
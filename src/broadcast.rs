@@ -0,0 +1,252 @@
+use bevy::prelude::*;
+
+use crate::board::PlayerTurn;
+use crate::clock::ChessClock;
+use crate::fen::to_fen;
+use crate::history::{MoveHistory, MoveRecord};
+use crate::pieces::*;
+
+use std::fmt::Write as _;
+use std::io::{self, Read, Write};
+use std::net::{TcpListener, TcpStream};
+use std::time::{Duration, Instant};
+
+/// Local address serving the game state to stream overlays.
+const BROADCAST_ADDR: &str = "127.0.0.1:8765";
+
+/// Time a client has to send its request and read the answer.
+const CLIENT_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Longest request read, only the request line matters.
+const MAX_REQUEST_LEN: usize = 8 * 1024;
+
+/// Listener of the broadcast endpoint, the last state published and the clients being served.
+struct Broadcast {
+    listener: Option<TcpListener>,
+    json: String,
+    clients: Vec<Client>,
+}
+
+/// Connection served over several frames, so that a slow client never stalls one.
+struct Client {
+    stream: TcpStream,
+    accepted: Instant,
+    request: Vec<u8>,
+    response: Vec<u8>,
+    sent: usize,
+}
+
+impl Client {
+    fn new(stream: TcpStream) -> Self {
+        Self {
+            stream,
+            accepted: Instant::now(),
+            request: Vec::new(),
+            response: Vec::new(),
+            sent: 0,
+        }
+    }
+
+    /// Read the request and write the answer as far as possible without blocking.
+    /// Returns true once the client is done with.
+    fn poll(&mut self, json: &str) -> io::Result<bool> {
+        if self.response.is_empty() {
+            let mut buffer = [0; 1024];
+            loop {
+                match self.stream.read(&mut buffer) {
+                    // Closed before the end of the request
+                    Ok(0) => return Ok(true),
+                    Ok(read) => self.request.extend_from_slice(&buffer[..read]),
+                    Err(err) if err.kind() == io::ErrorKind::WouldBlock => break,
+                    Err(err) => return Err(err),
+                }
+            }
+            let complete = self.request.windows(4).any(|end| end == b"\r\n\r\n");
+            if !complete && self.request.len() < MAX_REQUEST_LEN {
+                return Ok(false);
+            }
+            // The state is taken when the request is complete
+            self.response = format!(
+                "HTTP/1.1 200 OK\r\nContent-Type: application/json\r\nAccess-Control-Allow-Origin: *\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                json.len(),
+                json,
+            )
+            .into_bytes();
+        }
+
+        while self.sent < self.response.len() {
+            match self.stream.write(&self.response[self.sent..]) {
+                Ok(written) => self.sent += written,
+                Err(err) if err.kind() == io::ErrorKind::WouldBlock => return Ok(false),
+                Err(err) => return Err(err),
+            }
+        }
+        Ok(true)
+    }
+}
+
+impl Default for Broadcast {
+    fn default() -> Self {
+        let listener = TcpListener::bind(BROADCAST_ADDR)
+            .and_then(|listener| listener.set_nonblocking(true).map(|_| listener));
+        let listener = match listener {
+            Ok(listener) => {
+                info!("broadcasting game state on http://{}", BROADCAST_ADDR);
+                Some(listener)
+            }
+            Err(err) => {
+                warn!("cannot broadcast on {}: {}", BROADCAST_ADDR, err);
+                None
+            }
+        };
+        Self {
+            listener,
+            json: String::from("{}"),
+            clients: Vec::new(),
+        }
+    }
+}
+
+/// Broadcast Plugin
+///
+/// Publishes the current FEN, clocks and last move as JSON over HTTP, so that
+/// streamers can build overlays (e.g. an OBS browser source) on top of the game.
+pub struct BroadcastPlugin;
+
+impl Plugin for BroadcastPlugin {
+    fn build(&self, app: &mut AppBuilder) {
+        app.init_resource::<Broadcast>()
+            // The board state is refilled once the move has been applied
+            .add_system_to_stage(
+                CoreStage::PostUpdate,
+                update_broadcast
+                    .system()
+                    .label("update_broadcast")
+                    .after("board_state"),
+            )
+            .add_system_to_stage(
+                CoreStage::PostUpdate,
+                serve_broadcast.system().after("update_broadcast"),
+            );
+    }
+}
+
+/// Refresh the published state after each move.
+fn update_broadcast(
+    mut broadcast: ResMut<Broadcast>,
+    turn: Res<PlayerTurn>,
+    clock: Res<ChessClock>,
+    history: Res<MoveHistory>,
+    board: Res<BoardState>,
+) {
+    if !board.is_changed() && !history.is_changed() && !turn.is_changed() {
+        return;
+    }
+
+    let fen = to_fen(&board.pieces, turn.0, history.fullmove());
+    broadcast.json = state_json(&fen, &clock, history.moves.last());
+}
+
+/// Published state, with the last move in standard algebraic notation, without its annotations.
+fn state_json(fen: &str, clock: &ChessClock, last_move: Option<&MoveRecord>) -> String {
+    let last_move = match last_move {
+        Some(record) => json_string(&record.san),
+        None => String::from("null"),
+    };
+    format!(
+        "{{\"fen\":{},\"white_clock_ms\":{},\"black_clock_ms\":{},\"last_move\":{}}}",
+        json_string(fen),
+        clock.white.as_millis(),
+        clock.black.as_millis(),
+        last_move,
+    )
+}
+
+/// JSON string literal of the text, escaping quotes, backslashes and control characters.
+fn json_string(text: &str) -> String {
+    let mut json = String::from("\"");
+    for c in text.chars() {
+        match c {
+            '"' => json.push_str("\\\""),
+            '\\' => json.push_str("\\\\"),
+            '\n' => json.push_str("\\n"),
+            '\r' => json.push_str("\\r"),
+            '\t' => json.push_str("\\t"),
+            // Writing to a String cannot fail
+            c if c.is_control() => {
+                let _ = write!(json, "\\u{:04x}", c as u32);
+            }
+            c => json.push(c),
+        }
+    }
+    json.push('"');
+    json
+}
+
+/// Answer pending HTTP requests with the published state, without waiting on any client.
+fn serve_broadcast(mut broadcast: ResMut<Broadcast>) {
+    let broadcast = &mut *broadcast;
+    let listener = match &broadcast.listener {
+        Some(listener) => listener,
+        None => return,
+    };
+
+    while let Ok((stream, _)) = listener.accept() {
+        match stream.set_nonblocking(true) {
+            Ok(()) => broadcast.clients.push(Client::new(stream)),
+            Err(err) => warn!("broadcast client error: {}", err),
+        }
+    }
+
+    let json = &broadcast.json;
+    for mut client in std::mem::take(&mut broadcast.clients) {
+        match client.poll(json) {
+            Ok(true) => {}
+            Ok(false) if client.accepted.elapsed() < CLIENT_TIMEOUT => {
+                broadcast.clients.push(client)
+            }
+            Ok(false) => warn!("broadcast client timed out"),
+            Err(err) => warn!("broadcast client error: {}", err),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::clock::TimeControl;
+
+    #[test]
+    fn escapes_json_strings() {
+        assert_eq!(json_string("e4"), "\"e4\"");
+        assert_eq!(
+            json_string("a \"quote\"\\\n\t\u{1}"),
+            "\"a \\\"quote\\\"\\\\\\n\\t\\u0001\""
+        );
+    }
+
+    #[test]
+    fn publishes_the_last_move_without_its_comment() {
+        let piece = Piece {
+            color: PieceColor::White,
+            piece_type: PieceType::Pawn,
+            x: 1,
+            y: 4,
+        };
+        let record = MoveRecord {
+            piece,
+            to: (3, 4),
+            captured: None,
+            san: String::from("e4"),
+            nag: None,
+            comment: Some(String::from("line one\n\"line two\"")),
+        };
+        let clock = ChessClock::new(&TimeControl::default());
+        let json = state_json("8/8/8/8/4P3/8/8/8 b - - 0 1", &clock, Some(&record));
+        assert_eq!(
+            json,
+            "{\"fen\":\"8/8/8/8/4P3/8/8/8 b - - 0 1\",\"white_clock_ms\":600000,\
+             \"black_clock_ms\":600000,\"last_move\":\"e4\"}"
+        );
+    }
+}
@@ -0,0 +1,406 @@
+use bevy::input::mouse::MouseWheel;
+use bevy::prelude::*;
+use bevy::render::camera::PerspectiveProjection;
+use bevy_mod_picking::PickingCameraBundle;
+
+use crate::assets::AppState;
+use crate::board::{GameOverEvent, LoadPositionEvent, MoveEvent};
+use crate::config::PlayerColor;
+use crate::pieces::PieceColor;
+use crate::settings::Settings;
+
+use std::collections::VecDeque;
+
+/// Duration of the fly-in when a game starts, in seconds.
+const INTRO_DURATION: f32 = 2.0;
+/// Duration of the push-in towards the taken king, in seconds.
+const CUT_DURATION: f32 = 0.8;
+/// Part of the distance to the taken king covered by the push-in.
+const CUT_DISTANCE: f32 = 0.2;
+/// Duration of the swing towards the taken king, in seconds.
+const GAME_OVER_DURATION: f32 = 1.2;
+/// Distance of the camera from the taken king once framed.
+const GAME_OVER_DISTANCE: f32 = 7.0;
+/// Duration of the way back to the resting position, in seconds.
+const HOME_DURATION: f32 = 0.6;
+/// Speed of the auto camera orbit around the board, in radians per second.
+const ORBIT_SPEED: f32 = 0.1;
+/// Time without input after which the auto camera takes over, in seconds.
+const ORBIT_DELAY: f32 = 10.0;
+/// Shortest time between two moves framed by the auto camera, in seconds.
+const FRAME_INTERVAL: f64 = 10.0;
+/// Distance of the auto camera from a framed move.
+const FRAME_DISTANCE: f32 = 9.0;
+/// Duration of the swing towards a framed move, and of the way back, in seconds.
+const FRAME_DURATION: f32 = 1.0;
+/// Time the auto camera holds on a framed move, in seconds.
+const FRAME_HOLD: f32 = 1.5;
+
+/// Resting position of the camera, looking at the board from the side of the player.
+pub fn home_transform(player: PieceColor) -> Transform {
+    seen_from(
+        player,
+        Transform::from_matrix(Mat4::from_rotation_translation(
+            Quat::from_xyzw(-0.3, -0.5, -0.3, 0.5).normalize(),
+            Vec3::new(-7.0, 20.0, 4.0),
+        )),
+    )
+}
+
+/// Camera transform from White's side, turned around the board for Black.
+fn seen_from(player: PieceColor, transform: Transform) -> Transform {
+    match player {
+        PieceColor::White => transform,
+        PieceColor::Black => turned(transform, std::f32::consts::PI),
+    }
+}
+
+/// Transform turned by an angle around the vertical axis of the board.
+fn turned(transform: Transform, angle: f32) -> Transform {
+    let center = Vec3::new(3.5, 0.0, 3.5);
+    let turn = Quat::from_rotation_y(angle);
+    Transform {
+        translation: center + turn * (transform.translation - center),
+        rotation: turn * transform.rotation,
+        scale: transform.scale,
+    }
+}
+
+/// Camera move to a transform.
+#[derive(Clone)]
+struct Shot {
+    to: Transform,
+    duration: f32,
+}
+
+/// Camera moving through a sequence of shots.
+pub struct CameraAnimation {
+    from: Transform,
+    shots: VecDeque<Shot>,
+    timer: Timer,
+}
+
+impl CameraAnimation {
+    fn new(from: Transform, shots: Vec<Shot>) -> Self {
+        let duration = shots.first().map_or(0.0, |shot| shot.duration);
+        Self {
+            from,
+            shots: shots.into(),
+            timer: Timer::from_seconds(duration, false),
+        }
+    }
+
+    /// Jump to the end of the animation, returning the final transform.
+    fn skip(&mut self) -> Option<Transform> {
+        let end = self.shots.pop_back()?.to;
+        self.shots.clear();
+        self.from = end;
+        Some(end)
+    }
+}
+
+/// Camera orbiting the board for spectators, once the players leave it alone.
+#[derive(Default)]
+struct AutoCamera {
+    /// Time since the last user input, in seconds
+    idle: f32,
+    /// Whether the camera left its resting position to orbit
+    orbited: bool,
+    /// Time the last move was framed, in seconds since startup
+    last_frame: f64,
+}
+
+impl AutoCamera {
+    fn active(&self, settings: &Settings) -> bool {
+        settings.auto_camera && !settings.reduced_motion && self.idle >= ORBIT_DELAY
+    }
+}
+
+/// Smooth start and stop of camera moves.
+fn ease(t: f32) -> f32 {
+    t * t * (3.0 - 2.0 * t)
+}
+
+/// Spawn the camera looking at the board, and the light
+fn setup_camera(mut commands: Commands, player: Res<PlayerColor>) {
+    // Camera
+    commands
+        .spawn_bundle(PerspectiveCameraBundle {
+            transform: home_transform(player.0),
+            ..Default::default()
+        })
+        .insert_bundle(PickingCameraBundle::default());
+    // Light
+    commands.spawn_bundle(LightBundle {
+        transform: Transform::from_translation(Vec3::new(4.0, 8.0, 4.0)),
+        ..Default::default()
+    });
+}
+
+/// Fly the camera in from above the board when a game starts
+fn start_intro(
+    mut commands: Commands,
+    player: Res<PlayerColor>,
+    query: Query<Entity, (With<Camera>, With<PerspectiveProjection>)>,
+) {
+    let from = seen_from(
+        player.0,
+        Transform::from_translation(Vec3::new(-12.0, 35.0, -10.0))
+            .looking_at(Vec3::new(3.5, 0.0, 3.5), Vec3::Y),
+    );
+    for camera in query.iter() {
+        commands.entity(camera).insert(CameraAnimation::new(
+            from,
+            vec![Shot {
+                to: home_transform(player.0),
+                duration: INTRO_DURATION,
+            }],
+        ));
+    }
+}
+
+/// Swing the camera towards the losing king, then slightly push in if camera cuts are on
+fn frame_game_over(
+    mut commands: Commands,
+    player: Res<PlayerColor>,
+    settings: Res<Settings>,
+    mut game_over_events: EventReader<GameOverEvent>,
+    query: Query<(Entity, &Transform), With<PerspectiveProjection>>,
+) {
+    let (x, y) = match game_over_events
+        .iter()
+        .last()
+        .and_then(|event| event.square)
+    {
+        Some(square) => square,
+        None => return,
+    };
+
+    let square = Vec3::new(x as f32, 0.0, y as f32);
+    // Keep looking from the players' side, closer and lower
+    let direction = (home_transform(player.0).translation - square).normalize();
+    let to = Transform::from_translation(square + direction * GAME_OVER_DISTANCE)
+        .looking_at(square, Vec3::Y);
+    let mut shots = vec![Shot {
+        to,
+        duration: GAME_OVER_DURATION,
+    }];
+    if settings.camera_cuts && !settings.reduced_motion {
+        let mut pushed = to;
+        pushed.translation = to.translation.lerp(square, CUT_DISTANCE);
+        shots.push(Shot {
+            to: pushed,
+            duration: CUT_DURATION,
+        });
+    }
+    for (camera, transform) in query.iter() {
+        commands
+            .entity(camera)
+            .insert(CameraAnimation::new(*transform, shots.clone()));
+    }
+}
+
+/// Bring the camera back to its resting position when a new position is loaded
+fn return_home(
+    mut commands: Commands,
+    player: Res<PlayerColor>,
+    mut load_position_events: EventReader<LoadPositionEvent>,
+    query: Query<(Entity, &Transform), With<PerspectiveProjection>>,
+) {
+    if load_position_events.iter().last().is_none() {
+        return;
+    }
+
+    let home = home_transform(player.0);
+    for (camera, transform) in query.iter() {
+        if *transform == home {
+            continue;
+        }
+        commands.entity(camera).insert(CameraAnimation::new(
+            *transform,
+            vec![Shot {
+                to: home,
+                duration: HOME_DURATION,
+            }],
+        ));
+    }
+}
+
+/// Move the camera along its animation
+fn animate_camera(
+    mut commands: Commands,
+    time: Res<Time>,
+    settings: Res<Settings>,
+    mut query: Query<(Entity, &mut Transform, &mut CameraAnimation)>,
+) {
+    for (camera, mut transform, mut animation) in query.iter_mut() {
+        // Cut straight to the last shot
+        if settings.reduced_motion {
+            if let Some(end) = animation.skip() {
+                *transform = end;
+            }
+        }
+        animation.timer.tick(time.delta());
+
+        let to = match animation.shots.front() {
+            Some(shot) => shot.to,
+            None => {
+                commands.entity(camera).remove::<CameraAnimation>();
+                continue;
+            }
+        };
+        let t = ease(animation.timer.percent());
+        transform.translation = animation.from.translation.lerp(to.translation, t);
+        transform.rotation = animation.from.rotation.slerp(to.rotation, t);
+
+        if animation.timer.finished() {
+            animation.from = to;
+            animation.shots.pop_front();
+            if let Some(next) = animation.shots.front() {
+                animation.timer = Timer::from_seconds(next.duration, false);
+            }
+        }
+    }
+}
+
+/// Skip camera animations on any user input
+fn cancel_camera_animation(
+    mouse_buttons: Res<Input<MouseButton>>,
+    keys: Res<Input<KeyCode>>,
+    mut mouse_wheel_events: EventReader<MouseWheel>,
+    mut query: Query<(&mut Transform, &mut CameraAnimation)>,
+) {
+    let scrolled = mouse_wheel_events.iter().count() > 0;
+    if !scrolled
+        && mouse_buttons.get_just_pressed().next().is_none()
+        && keys.get_just_pressed().next().is_none()
+    {
+        return;
+    }
+
+    // The animation is removed once found empty
+    for (mut transform, mut animation) in query.iter_mut() {
+        if let Some(end) = animation.skip() {
+            *transform = end;
+        }
+    }
+}
+
+/// Slowly orbit the board while the auto camera is on and nobody touches the game.
+/// Any input hands the camera back at once, in its resting position.
+fn orbit_camera(
+    mut commands: Commands,
+    time: Res<Time>,
+    settings: Res<Settings>,
+    player: Res<PlayerColor>,
+    mouse_buttons: Res<Input<MouseButton>>,
+    keys: Res<Input<KeyCode>>,
+    mut mouse_wheel_events: EventReader<MouseWheel>,
+    mut auto_camera: ResMut<AutoCamera>,
+    mut query: Query<
+        (Entity, &mut Transform, Option<&CameraAnimation>),
+        With<PerspectiveProjection>,
+    >,
+) {
+    let scrolled = mouse_wheel_events.iter().count() > 0;
+    if scrolled
+        || mouse_buttons.get_just_pressed().next().is_some()
+        || keys.get_just_pressed().next().is_some()
+    {
+        auto_camera.idle = 0.0;
+    } else {
+        auto_camera.idle += time.delta_seconds();
+    }
+
+    if !auto_camera.active(&settings) {
+        if auto_camera.orbited {
+            auto_camera.orbited = false;
+            for (camera, mut transform, _) in query.iter_mut() {
+                commands.entity(camera).remove::<CameraAnimation>();
+                *transform = home_transform(player.0);
+            }
+        }
+        return;
+    }
+
+    // Turn from wherever other camera moves left the camera, e.g. the end of the game
+    let angle = ORBIT_SPEED * time.delta_seconds();
+    for (_, mut transform, animation) in query.iter_mut() {
+        if animation.is_none() {
+            *transform = turned(*transform, angle);
+            auto_camera.orbited = true;
+        }
+    }
+}
+
+/// Now and then, swing the orbiting camera towards the last move, then back
+fn frame_last_move(
+    mut commands: Commands,
+    time: Res<Time>,
+    settings: Res<Settings>,
+    mut auto_camera: ResMut<AutoCamera>,
+    mut move_events: EventReader<MoveEvent>,
+    query: Query<(Entity, &Transform), (With<PerspectiveProjection>, Without<CameraAnimation>)>,
+) {
+    let event = match move_events.iter().last() {
+        Some(event) => event,
+        None => return,
+    };
+    let now = time.seconds_since_startup();
+    if !auto_camera.active(&settings) || now - auto_camera.last_frame < FRAME_INTERVAL {
+        return;
+    }
+    auto_camera.last_frame = now;
+    auto_camera.orbited = true;
+    let square = Vec3::new(event.to.0 as f32, 0.0, event.to.1 as f32);
+    for (camera, transform) in query.iter() {
+        let direction = (transform.translation - square).normalize();
+        let framed = Transform::from_translation(square + direction * FRAME_DISTANCE)
+            .looking_at(square, Vec3::Y);
+        commands.entity(camera).insert(CameraAnimation::new(
+            *transform,
+            vec![
+                Shot {
+                    to: framed,
+                    duration: FRAME_DURATION,
+                },
+                Shot {
+                    to: framed,
+                    duration: FRAME_HOLD,
+                },
+                Shot {
+                    to: *transform,
+                    duration: FRAME_DURATION,
+                },
+            ],
+        ));
+    }
+}
+
+/// Camera Plugin
+pub struct CameraPlugin;
+
+impl Plugin for CameraPlugin {
+    fn build(&self, app: &mut AppBuilder) {
+        app.init_resource::<AutoCamera>()
+            .add_startup_system(setup_camera.system())
+            .add_system_set(
+                SystemSet::on_enter(AppState::Playing).with_system(start_intro.system()),
+            )
+            .add_system(frame_game_over.system().after("remove_taken_piece"))
+            .add_system(return_home.system())
+            .add_system(
+                cancel_camera_animation
+                    .system()
+                    .label("cancel_camera_animation"),
+            )
+            .add_system(
+                animate_camera
+                    .system()
+                    .label("animate_camera")
+                    .after("cancel_camera_animation"),
+            )
+            .add_system(orbit_camera.system().after("animate_camera"))
+            .add_system(frame_last_move.system().after("play_move"));
+    }
+}
@@ -0,0 +1,521 @@
+use bevy::prelude::*;
+
+use crate::assets::AppState;
+use crate::board::{GameOverEvent, GameResult, LoadPositionEvent, MoveEvent, PlayerTurn};
+use crate::config::GameConfig;
+use crate::pieces::{BoardState, PieceColor, PieceType};
+
+use std::fmt;
+use std::time::Duration;
+
+/// Remaining time under which a clock is in time scramble.
+pub const LOW_TIME: Duration = Duration::from_secs(10);
+
+/// How time is given back to a player for each move.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum ClockMode {
+    /// No time is ever added.
+    SuddenDeath,
+    /// Fischer increment: a fixed amount is added after each move.
+    Increment(Duration),
+    /// Bronstein delay: time used for the move is given back after it, up to the delay.
+    Bronstein(Duration),
+    /// Simple delay: the clock only starts running once the delay has elapsed.
+    Delay(Duration),
+    /// Correspondence: each move must be made within the limit, the clock resets every move.
+    Correspondence(Duration),
+}
+
+/// Time control used to set up the clock.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct TimeControl {
+    /// Starting time of each player
+    pub initial: Duration,
+    /// Time added for each move
+    pub mode: ClockMode,
+}
+
+impl TimeControl {
+    /// Read a time control written as `<MINUTES>` for sudden death, `<MINUTES>+<SECONDS>`
+    /// for a Fischer increment, `bronstein:<MINUTES>+<SECONDS>` or `delay:<MINUTES>+<SECONDS>`
+    /// for a delay, or `correspondence:<MINUTES>` for a time limit per move.
+    pub fn parse(spec: &str) -> Option<Self> {
+        let (kind, times) = match spec.find(':') {
+            Some(i) => (&spec[..i], &spec[i + 1..]),
+            None => ("fischer", spec),
+        };
+        let (minutes, seconds) = match times.find('+') {
+            Some(i) => (&times[..i], Some(&times[i + 1..])),
+            None => (times, None),
+        };
+        let parse = |value: &str, unit: f32| {
+            value
+                .parse::<f32>()
+                .ok()
+                .filter(|value| value.is_finite() && *value >= 0.0)
+                .map(|value| Duration::from_secs_f32(value * unit))
+        };
+        let initial = parse(minutes, 60.0).filter(|initial| *initial > Duration::ZERO)?;
+        let extra = match seconds {
+            Some(seconds) => Some(parse(seconds, 1.0)?),
+            None => None,
+        };
+
+        let mode = match (kind, extra) {
+            ("fischer", None) => ClockMode::SuddenDeath,
+            ("fischer", Some(increment)) if increment == Duration::ZERO => ClockMode::SuddenDeath,
+            ("fischer", Some(increment)) => ClockMode::Increment(increment),
+            ("bronstein", Some(delay)) => ClockMode::Bronstein(delay),
+            ("delay", Some(delay)) => ClockMode::Delay(delay),
+            ("correspondence", None) => ClockMode::Correspondence(initial),
+            _ => return None,
+        };
+        Some(Self { initial, mode })
+    }
+
+    /// Time on each clock when the game starts.
+    pub fn starting_time(&self) -> Duration {
+        match self.mode {
+            ClockMode::Correspondence(limit) => limit,
+            _ => self.initial,
+        }
+    }
+}
+
+/// Short form of a duration, e.g. "10 min" or "2 s".
+fn format_duration(duration: Duration) -> String {
+    let secs = duration.as_secs();
+    if secs >= 60 && secs % 60 == 0 {
+        format!("{} min", secs / 60)
+    } else {
+        format!("{} s", secs)
+    }
+}
+
+impl fmt::Display for TimeControl {
+    /// Written as on tournament sheets, e.g. "10 min + 2 s".
+    fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        let initial = format_duration(self.initial);
+        match self.mode {
+            ClockMode::SuddenDeath => write!(formatter, "{}", initial),
+            ClockMode::Increment(increment) => {
+                write!(formatter, "{} + {}", initial, format_duration(increment))
+            }
+            ClockMode::Bronstein(delay) => {
+                write!(
+                    formatter,
+                    "{}, Bronstein {}",
+                    initial,
+                    format_duration(delay)
+                )
+            }
+            ClockMode::Delay(delay) => {
+                write!(formatter, "{}, delay {}", initial, format_duration(delay))
+            }
+            ClockMode::Correspondence(limit) => {
+                write!(formatter, "{} / move", format_duration(limit))
+            }
+        }
+    }
+}
+
+impl Default for TimeControl {
+    fn default() -> Self {
+        Self {
+            initial: Duration::from_secs(10 * 60),
+            mode: ClockMode::SuddenDeath,
+        }
+    }
+}
+
+/// Remaining time of both players.
+pub struct ChessClock {
+    pub white: Duration,
+    pub black: Duration,
+    /// Time spent on the current move
+    pub spent: Duration,
+}
+
+impl ChessClock {
+    /// Clock at the start of a game with the given time control.
+    pub fn new(time_control: &TimeControl) -> Self {
+        Self {
+            white: time_control.starting_time(),
+            black: time_control.starting_time(),
+            spent: Duration::ZERO,
+        }
+    }
+
+    /// Remaining time of the given player.
+    pub fn remaining(&self, color: PieceColor) -> Duration {
+        match color {
+            PieceColor::White => self.white,
+            PieceColor::Black => self.black,
+        }
+    }
+
+    fn remaining_mut(&mut self, color: PieceColor) -> &mut Duration {
+        match color {
+            PieceColor::White => &mut self.white,
+            PieceColor::Black => &mut self.black,
+        }
+    }
+
+    /// Run down the clock of the player to move by the time elapsed.
+    /// Under a simple delay, only the time spent on the move past the delay is charged.
+    pub fn tick(&mut self, turn: PieceColor, mode: ClockMode, elapsed: Duration) {
+        let spent_before = self.spent;
+        self.spent += elapsed;
+
+        let charged = match mode {
+            ClockMode::Delay(delay) => {
+                self.spent.saturating_sub(delay) - spent_before.saturating_sub(delay)
+            }
+            _ => elapsed,
+        };
+        let remaining = self.remaining_mut(turn);
+        *remaining = remaining.saturating_sub(charged);
+    }
+
+    /// Give time back to the player who just moved, according to the clock mode,
+    /// and start timing the next move.
+    pub fn end_move(&mut self, moved: PieceColor, mode: ClockMode) {
+        let spent = self.spent;
+        self.spent = Duration::ZERO;
+
+        let remaining = self.remaining_mut(moved);
+        // A flagged player gets no time back
+        if *remaining == Duration::ZERO {
+            return;
+        }
+        match mode {
+            ClockMode::SuddenDeath | ClockMode::Delay(_) => {}
+            ClockMode::Increment(increment) => *remaining += increment,
+            ClockMode::Bronstein(delay) => *remaining += spent.min(delay),
+            // The whole limit is available again for the next move
+            ClockMode::Correspondence(limit) => *remaining = limit,
+        }
+    }
+
+    /// State of the clock of the given player.
+    pub fn state(&self, color: PieceColor) -> ClockState {
+        let remaining = self.remaining(color);
+        if remaining == Duration::ZERO {
+            ClockState::Flagged
+        } else if remaining < LOW_TIME {
+            ClockState::LowTime
+        } else {
+            ClockState::Normal
+        }
+    }
+}
+
+impl FromWorld for ChessClock {
+    fn from_world(world: &mut World) -> Self {
+        let config = world.get_resource_or_insert_with(GameConfig::default);
+        Self::new(&config.time_control)
+    }
+}
+
+/// State of a player's clock.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum ClockState {
+    Normal,
+    LowTime,
+    Flagged,
+}
+
+impl Default for ClockState {
+    fn default() -> Self {
+        ClockState::Normal
+    }
+}
+
+/// Event sent whenever the state of a player's clock changes.
+pub struct ClockStateEvent {
+    pub color: PieceColor,
+    pub state: ClockState,
+}
+
+/// Event sent whenever a clock reading changes by a tenth of a second, the finest
+/// step shown, so that displays of the clock only update when needed.
+pub struct ClockTickEvent;
+
+/// Last clock states seen, to only report transitions.
+#[derive(Default)]
+struct LastClockStates {
+    white: ClockState,
+    black: ClockState,
+}
+
+/// Clock Plugin
+pub struct ClockPlugin;
+
+impl Plugin for ClockPlugin {
+    fn build(&self, app: &mut AppBuilder) {
+        app.init_resource::<ChessClock>()
+            .add_event::<ClockStateEvent>()
+            .add_event::<ClockTickEvent>()
+            .add_system_set(
+                SystemSet::on_enter(AppState::Playing).with_system(start_clock.system()),
+            )
+            .add_system(reset_clock.system().before("tick_clock"))
+            // The clocks are stopped while loading and once the game is over
+            .add_system_set(
+                SystemSet::on_update(AppState::Playing)
+                    .with_system(tick_clock.system().label("tick_clock").before("play_move")),
+            )
+            // Moves are only timed when played, not when a position sets the turn
+            .add_system(
+                switch_clock
+                    .system()
+                    .label("switch_clock")
+                    .after("tick_clock")
+                    .after("play_move"),
+            )
+            .add_system(flag_fall.system().after("switch_clock"))
+            .add_system(send_clock_state_events.system().after("switch_clock"))
+            .add_system(send_clock_tick_events.system().after("switch_clock"));
+    }
+}
+
+/// Set the clock up with the configured time control when the game starts.
+fn start_clock(config: Res<GameConfig>, mut clock: ResMut<ChessClock>) {
+    *clock = ChessClock::new(&config.time_control);
+}
+
+/// Restart the clock when a new position is loaded.
+fn reset_clock(
+    config: Res<GameConfig>,
+    mut load_position_events: EventReader<LoadPositionEvent>,
+    mut clock: ResMut<ChessClock>,
+) {
+    if load_position_events.iter().last().is_some() {
+        // The time spent on the move goes too, so no delay or Bronstein credit carries over
+        *clock = ChessClock::new(&config.time_control);
+    }
+}
+
+/// Run down the clock of the player to move.
+fn tick_clock(
+    time: Res<Time>,
+    turn: Res<PlayerTurn>,
+    config: Res<GameConfig>,
+    game_result: Res<GameResult>,
+    mut clock: ResMut<ChessClock>,
+) {
+    if game_result.0.is_some() {
+        return;
+    }
+    clock.tick(turn.0, config.time_control.mode, time.delta());
+}
+
+/// Give time back to the player who just moved, according to the clock mode.
+fn switch_clock(
+    config: Res<GameConfig>,
+    mut move_events: EventReader<MoveEvent>,
+    mut clock: ResMut<ChessClock>,
+) {
+    for event in move_events.iter() {
+        clock.end_move(event.piece.color, config.time_control.mode);
+    }
+}
+
+/// End the game when the player to move runs out of time.
+fn flag_fall(
+    turn: Res<PlayerTurn>,
+    clock: Res<ChessClock>,
+    board: Res<BoardState>,
+    mut game_result: ResMut<GameResult>,
+    mut game_over_events: EventWriter<GameOverEvent>,
+) {
+    if game_result.0.is_some() || clock.state(turn.0) != ClockState::Flagged {
+        return;
+    }
+
+    let winner = turn.0.opponent();
+    game_result.0 = Some(winner);
+    let square = board
+        .pieces
+        .iter()
+        .find(|piece| piece.piece_type == PieceType::King && piece.color == turn.0)
+        .map(|king| (king.x, king.y));
+    game_over_events.send(GameOverEvent { winner, square });
+}
+
+/// Send an event when the remaining times or the time spent on the move change
+/// by a tenth of a second.
+fn send_clock_tick_events(
+    clock: Res<ChessClock>,
+    mut last: Local<Option<[u128; 3]>>,
+    mut clock_tick_events: EventWriter<ClockTickEvent>,
+) {
+    if !clock.is_changed() {
+        return;
+    }
+
+    let tenths = [
+        clock.white.as_millis() / 100,
+        clock.black.as_millis() / 100,
+        clock.spent.as_millis() / 100,
+    ];
+    if *last != Some(tenths) {
+        *last = Some(tenths);
+        clock_tick_events.send(ClockTickEvent);
+    }
+}
+
+/// Send an event for each player whose clock state changed.
+fn send_clock_state_events(
+    clock: Res<ChessClock>,
+    mut last: Local<LastClockStates>,
+    mut clock_state_events: EventWriter<ClockStateEvent>,
+) {
+    if !clock.is_changed() {
+        return;
+    }
+
+    for &color in [PieceColor::White, PieceColor::Black].iter() {
+        let state = clock.state(color);
+        let last_state = match color {
+            PieceColor::White => &mut last.white,
+            PieceColor::Black => &mut last.black,
+        };
+        if *last_state != state {
+            *last_state = state;
+            clock_state_events.send(ClockStateEvent { color, state });
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn secs(secs: u64) -> Duration {
+        Duration::from_secs(secs)
+    }
+
+    /// Clock of a one minute game, after White spent the given times on its moves.
+    fn play(mode: ClockMode, moves: &[u64]) -> ChessClock {
+        let mut clock = ChessClock::new(&TimeControl {
+            initial: secs(60),
+            mode,
+        });
+        for &spent in moves {
+            clock.tick(PieceColor::White, mode, secs(spent));
+            clock.end_move(PieceColor::White, mode);
+        }
+        clock
+    }
+
+    #[test]
+    fn sudden_death_adds_nothing() {
+        let clock = play(ClockMode::SuddenDeath, &[5, 10]);
+        assert_eq!(clock.remaining(PieceColor::White), secs(45));
+        assert_eq!(clock.remaining(PieceColor::Black), secs(60));
+    }
+
+    #[test]
+    fn increment_adds_after_each_move() {
+        let clock = play(ClockMode::Increment(secs(2)), &[5, 10]);
+        assert_eq!(clock.remaining(PieceColor::White), secs(49));
+    }
+
+    #[test]
+    fn bronstein_gives_back_up_to_the_delay() {
+        let clock = play(ClockMode::Bronstein(secs(3)), &[2]);
+        assert_eq!(clock.remaining(PieceColor::White), secs(60));
+        let clock = play(ClockMode::Bronstein(secs(3)), &[2, 5]);
+        assert_eq!(clock.remaining(PieceColor::White), secs(58));
+    }
+
+    #[test]
+    fn delay_charges_time_past_the_delay() {
+        let mode = ClockMode::Delay(secs(3));
+        let mut clock = play(mode, &[2]);
+        assert_eq!(clock.remaining(PieceColor::White), secs(60));
+
+        // Ticks within one move add up before the delay runs out
+        clock.tick(PieceColor::White, mode, secs(2));
+        clock.tick(PieceColor::White, mode, secs(2));
+        assert_eq!(clock.remaining(PieceColor::White), secs(59));
+
+        // The delay starts again on the next move
+        clock.end_move(PieceColor::White, mode);
+        clock.tick(PieceColor::White, mode, secs(2));
+        assert_eq!(clock.remaining(PieceColor::White), secs(59));
+    }
+
+    #[test]
+    fn parses_time_controls() {
+        let parse = |spec| TimeControl::parse(spec).map(|control| (control.initial, control.mode));
+        assert_eq!(parse("10"), Some((secs(600), ClockMode::SuddenDeath)));
+        assert_eq!(
+            parse("3+2"),
+            Some((secs(180), ClockMode::Increment(secs(2))))
+        );
+        assert_eq!(parse("5+0"), Some((secs(300), ClockMode::SuddenDeath)));
+        assert_eq!(parse("0.5"), Some((secs(30), ClockMode::SuddenDeath)));
+        assert_eq!(
+            parse("bronstein:5+3"),
+            Some((secs(300), ClockMode::Bronstein(secs(3))))
+        );
+        assert_eq!(
+            parse("delay:5+3"),
+            Some((secs(300), ClockMode::Delay(secs(3))))
+        );
+        assert_eq!(
+            parse("correspondence:30"),
+            Some((secs(1800), ClockMode::Correspondence(secs(1800))))
+        );
+    }
+
+    #[test]
+    fn rejects_bad_time_controls() {
+        for spec in [
+            "",
+            "0",
+            "-5",
+            "abc",
+            "5+",
+            "3+x",
+            "delay:5",
+            "correspondence:5+2",
+            "blitz:5+2",
+        ]
+        .iter()
+        {
+            assert_eq!(TimeControl::parse(spec), None, "{}", spec);
+        }
+    }
+
+    #[test]
+    fn correspondence_resets_to_the_limit() {
+        let clock = play(ClockMode::Correspondence(secs(30)), &[]);
+        assert_eq!(clock.remaining(PieceColor::White), secs(30));
+        let clock = play(ClockMode::Correspondence(secs(30)), &[25, 20]);
+        assert_eq!(clock.remaining(PieceColor::White), secs(30));
+    }
+
+    #[test]
+    fn flagged_player_gets_no_time_back() {
+        let modes = [
+            ClockMode::Increment(secs(2)),
+            ClockMode::Bronstein(secs(3)),
+            ClockMode::Correspondence(secs(60)),
+        ];
+        for &mode in modes.iter() {
+            let clock = play(mode, &[90]);
+            assert_eq!(clock.remaining(PieceColor::White), Duration::ZERO);
+            assert_eq!(clock.state(PieceColor::White), ClockState::Flagged);
+        }
+    }
+
+    #[test]
+    fn low_time_below_threshold() {
+        let clock = play(ClockMode::SuddenDeath, &[55]);
+        assert_eq!(clock.state(PieceColor::White), ClockState::LowTime);
+        assert_eq!(clock.state(PieceColor::Black), ClockState::Normal);
+    }
+}
@@ -0,0 +1,123 @@
+use bevy::prelude::*;
+use bevy::render::camera::PerspectiveProjection;
+use bevy::window::WindowResized;
+
+use crate::locale::*;
+use crate::pieces::PieceColor;
+use crate::settings::Settings;
+use crate::ui::UiAssets;
+
+/// Width and height of the compass, in pixels.
+const COMPASS_SIZE: f32 = 80.0;
+/// Distance of the side marks from the center of the compass, in pixels.
+const COMPASS_RADIUS: f32 = 26.0;
+/// Half the size of a side mark, roughly centering it on its anchor.
+const MARK_OFFSET: f32 = 8.0;
+
+/// Mark on the compass pointing towards the side of a player
+struct CompassMark(PieceColor);
+
+/// Initialize the compass at the top of the window
+fn init_compass(mut commands: Commands, assets: Res<UiAssets>) {
+    commands
+        .spawn_bundle(NodeBundle {
+            style: Style {
+                position_type: PositionType::Absolute,
+                position: Rect {
+                    left: Val::Percent(50.0),
+                    top: Val::Px(10.0),
+                    ..Default::default()
+                },
+                margin: Rect {
+                    left: Val::Px(-COMPASS_SIZE / 2.0),
+                    ..Default::default()
+                },
+                size: Size::new(Val::Px(COMPASS_SIZE), Val::Px(COMPASS_SIZE)),
+                ..Default::default()
+            },
+            material: assets.panel.clone(),
+            ..Default::default()
+        })
+        .with_children(|parent| {
+            for &color in [PieceColor::White, PieceColor::Black].iter() {
+                let mut text = assets.text("", 20.0);
+                if color == PieceColor::Black {
+                    text.sections[0].style.color = Color::rgb(0.4, 0.7, 0.7);
+                }
+                parent
+                    .spawn_bundle(TextBundle {
+                        style: Style {
+                            position_type: PositionType::Absolute,
+                            ..Default::default()
+                        },
+                        text,
+                        ..Default::default()
+                    })
+                    .insert(CompassMark(color));
+            }
+        });
+}
+
+/// Point the compass marks towards the side of each player, as seen from the camera
+fn update_compass(
+    windows: Res<Windows>,
+    settings: Res<Settings>,
+    mut window_resized_events: EventReader<WindowResized>,
+    camera_query: Query<(&Camera, &GlobalTransform), With<PerspectiveProjection>>,
+    moved_camera_query: Query<(), (With<PerspectiveProjection>, Changed<GlobalTransform>)>,
+    mut marks: Query<(&CompassMark, &mut Style, &mut Text)>,
+) {
+    let resized = window_resized_events.iter().last().is_some();
+    if !settings.is_changed() && !resized && moved_camera_query.iter().next().is_none() {
+        return;
+    }
+    let (camera, camera_transform) = match camera_query.iter().next() {
+        Some(camera) => camera,
+        None => return,
+    };
+
+    // White's edge of the board, from its center
+    let to_screen = |point: Vec3| camera.world_to_screen(&windows, camera_transform, point);
+    let direction = match (
+        to_screen(Vec3::new(3.5, 0.0, 3.5)),
+        to_screen(Vec3::new(-0.5, 0.0, 3.5)),
+    ) {
+        (Some(center), Some(white)) => (white - center).normalize_or_zero(),
+        _ => return,
+    };
+
+    for (mark, mut style, mut text) in marks.iter_mut() {
+        let direction = match mark.0 {
+            PieceColor::White => direction,
+            PieceColor::Black => -direction,
+        };
+        let position = Vec2::splat(COMPASS_SIZE / 2.0 - MARK_OFFSET) + direction * COMPASS_RADIUS;
+        // Avoid relayouting the compass every frame
+        let left = Val::Px(position.x.round());
+        let bottom = Val::Px(position.y.round());
+        if style.position.left != left || style.position.bottom != bottom {
+            style.position.left = left;
+            style.position.bottom = bottom;
+        }
+
+        let letter: String = color_name(settings.language, mark.0)
+            .chars()
+            .take(1)
+            .collect();
+        if text.sections[0].value != letter {
+            text.sections[0].value = letter;
+        }
+    }
+}
+
+/// Compass Plugin
+///
+/// Shows which side of the board belongs to each player, turning with the camera.
+pub struct CompassPlugin;
+
+impl Plugin for CompassPlugin {
+    fn build(&self, app: &mut AppBuilder) {
+        app.add_startup_system(init_compass.system())
+            .add_system(update_compass.system());
+    }
+}
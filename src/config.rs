@@ -0,0 +1,283 @@
+use bevy::prelude::*;
+
+use crate::clock::{ClockMode, TimeControl};
+use crate::fen::{parse_fen, FenError, Position, STARTING_FEN};
+use crate::pieces::PieceColor;
+
+use std::fmt;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// Number below `n`, good enough for casual choices without pulling in a random crate.
+pub(crate) fn random_below(n: usize) -> usize {
+    let nanos = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |time| time.subsec_nanos());
+    (nanos / 1000) as usize % n.max(1)
+}
+
+/// Side chosen by the local player before the game.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum ColorChoice {
+    White,
+    Black,
+    /// Drawn when the game starts
+    Random,
+}
+
+impl ColorChoice {
+    /// Color of the player, drawing one for `Random`.
+    pub fn resolve(self) -> PieceColor {
+        match self {
+            ColorChoice::White => PieceColor::White,
+            ColorChoice::Black => PieceColor::Black,
+            ColorChoice::Random => {
+                if random_below(2) == 0 {
+                    PieceColor::White
+                } else {
+                    PieceColor::Black
+                }
+            }
+        }
+    }
+}
+
+/// Color of the local player, whose side of the board faces the camera.
+pub struct PlayerColor(pub PieceColor);
+
+impl FromWorld for PlayerColor {
+    fn from_world(world: &mut World) -> Self {
+        let config = world.get_resource_or_insert_with(GameConfig::default);
+        Self(config.player.resolve())
+    }
+}
+
+/// Error parsing command-line arguments.
+#[derive(Debug, Clone, PartialEq)]
+pub enum ArgsError {
+    /// The flag is not known.
+    UnknownFlag(String),
+    /// The flag needs a value, e.g. `--fen <FEN>`.
+    MissingValue(String),
+    /// The flag is known but the game cannot do it yet.
+    Unsupported(String),
+    /// The value of `--fen` is not a valid FEN.
+    BadFen(FenError),
+    /// The value of `--color` is not `white`, `black` or `random`.
+    BadColor(String),
+    /// The value of `--move-limit` is not a number of seconds.
+    BadMoveLimit(String),
+    /// The value of `--time-control` is not a time control, e.g. `3+2`.
+    BadTimeControl(String),
+    /// The value of `--match` is not a positive number of games.
+    BadMatchGames(String),
+}
+
+impl fmt::Display for ArgsError {
+    fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ArgsError::UnknownFlag(flag) => write!(formatter, "unknown flag {}", flag),
+            ArgsError::MissingValue(flag) => write!(formatter, "missing value for {}", flag),
+            ArgsError::Unsupported(flag) => write!(formatter, "{} is not supported yet", flag),
+            ArgsError::BadFen(err) => write!(formatter, "invalid FEN: {}", err),
+            ArgsError::BadColor(color) => write!(formatter, "invalid color {}", color),
+            ArgsError::BadMoveLimit(limit) => write!(formatter, "invalid move limit {}", limit),
+            ArgsError::BadTimeControl(time_control) => {
+                write!(formatter, "invalid time control {}", time_control)
+            }
+            ArgsError::BadMatchGames(games) => {
+                write!(formatter, "invalid number of games {}", games)
+            }
+        }
+    }
+}
+
+/// Setup of a game, read by the plugins when the game starts.
+///
+/// ```no_run
+/// use bevy::prelude::*;
+/// use bevy_chess::clock::{ClockMode, TimeControl};
+/// use bevy_chess::GameConfig;
+/// use std::time::Duration;
+///
+/// App::build()
+///     .insert_resource(
+///         GameConfig::new()
+///             .starting_fen("8/8/8/4k3/8/8/8/3QK3 w - - 0 1")
+///             .time_control(TimeControl {
+///                 initial: Duration::from_secs(3 * 60),
+///                 mode: ClockMode::Increment(Duration::from_secs(2)),
+///             }),
+///     )
+///     .add_plugins(DefaultPlugins)
+///     .add_plugins(bevy_chess::ChessPlugins)
+///     .run();
+/// ```
+#[derive(Clone, Debug)]
+pub struct GameConfig {
+    /// FEN of the starting position
+    pub starting_fen: String,
+    /// Time control of both clocks
+    pub time_control: TimeControl,
+    /// Side of the local player
+    pub player: ColorChoice,
+    /// Time after which a random move is played for a player, for casual hotseat games
+    pub move_time_limit: Option<Duration>,
+    /// Piece kit to use instead of the default one, unless a `KitConfig` is inserted
+    pub kit: Option<String>,
+    /// Number of games of a match, the players swapping colors after each game
+    pub match_games: Option<u32>,
+}
+
+impl Default for GameConfig {
+    fn default() -> Self {
+        Self {
+            starting_fen: STARTING_FEN.to_string(),
+            time_control: TimeControl::default(),
+            player: ColorChoice::White,
+            move_time_limit: None,
+            kit: None,
+            match_games: None,
+        }
+    }
+}
+
+impl GameConfig {
+    /// Standard game with the default time control.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Start from the given FEN instead of the standard position.
+    pub fn starting_fen(mut self, fen: impl Into<String>) -> Self {
+        self.starting_fen = fen.into();
+        self
+    }
+
+    /// Play with the given time control.
+    pub fn time_control(mut self, time_control: TimeControl) -> Self {
+        self.time_control = time_control;
+        self
+    }
+
+    /// Play the given side, facing the camera.
+    pub fn player(mut self, player: ColorChoice) -> Self {
+        self.player = player;
+        self
+    }
+
+    /// Play a random move for players who think longer than the limit.
+    pub fn move_time_limit(mut self, limit: Duration) -> Self {
+        self.move_time_limit = Some(limit);
+        self
+    }
+
+    /// Use the piece kit described by the given `.ron` file, falling back to the
+    /// default kit if it cannot be loaded.
+    pub fn kit(mut self, path: impl Into<String>) -> Self {
+        self.kit = Some(path.into());
+        self
+    }
+
+    /// Play a match over the given number of games, ending early once one
+    /// player cannot be caught.
+    pub fn match_games(mut self, games: u32) -> Self {
+        self.match_games = Some(games);
+        self
+    }
+
+    /// Configuration from command-line arguments, without the program name.
+    /// Values are given as `--fen <FEN>` or `--fen=<FEN>`.
+    pub fn from_args(args: impl IntoIterator<Item = String>) -> Result<Self, ArgsError> {
+        let mut config = Self::new();
+        let mut args = args.into_iter();
+        while let Some(arg) = args.next() {
+            let (flag, value) = match arg.find('=') {
+                Some(i) => (arg[..i].to_string(), Some(arg[i + 1..].to_string())),
+                None => (arg, None),
+            };
+            match flag.as_str() {
+                "--fen" => {
+                    let fen = match value.or_else(|| args.next()) {
+                        Some(fen) => fen,
+                        None => return Err(ArgsError::MissingValue(flag)),
+                    };
+                    parse_fen(&fen).map_err(ArgsError::BadFen)?;
+                    config.starting_fen = fen;
+                }
+                "--color" => {
+                    config.player = match value.or_else(|| args.next()).as_deref() {
+                        Some("white") => ColorChoice::White,
+                        Some("black") => ColorChoice::Black,
+                        Some("random") => ColorChoice::Random,
+                        Some(color) => return Err(ArgsError::BadColor(color.to_string())),
+                        None => return Err(ArgsError::MissingValue(flag)),
+                    };
+                }
+                "--move-limit" => {
+                    let limit = match value.or_else(|| args.next()) {
+                        Some(limit) => limit,
+                        None => return Err(ArgsError::MissingValue(flag)),
+                    };
+                    let seconds = limit
+                        .parse::<f32>()
+                        .ok()
+                        .filter(|seconds| *seconds > 0.0)
+                        .ok_or(ArgsError::BadMoveLimit(limit))?;
+                    config.move_time_limit = Some(Duration::from_secs_f32(seconds));
+                }
+                "--kit" => {
+                    config.kit = match value.or_else(|| args.next()) {
+                        Some(path) => Some(path),
+                        None => return Err(ArgsError::MissingValue(flag)),
+                    };
+                }
+                "--time-control" => {
+                    let spec = match value.or_else(|| args.next()) {
+                        Some(spec) => spec,
+                        None => return Err(ArgsError::MissingValue(flag)),
+                    };
+                    config.time_control =
+                        TimeControl::parse(&spec).ok_or(ArgsError::BadTimeControl(spec))?;
+                }
+                "--match" => {
+                    let games = match value.or_else(|| args.next()) {
+                        Some(games) => games,
+                        None => return Err(ArgsError::MissingValue(flag)),
+                    };
+                    let count = games
+                        .parse::<u32>()
+                        .ok()
+                        .filter(|count| *count > 0)
+                        .ok_or(ArgsError::BadMatchGames(games))?;
+                    config.match_games = Some(count);
+                }
+                // Known flags of features the game does not have yet
+                "--pgn" | "--ai-level" | "--headless" | "--white" | "--black" => {
+                    return Err(ArgsError::Unsupported(flag));
+                }
+                _ => return Err(ArgsError::UnknownFlag(flag)),
+            }
+        }
+        Ok(config)
+    }
+
+    /// Time allowed for each move, by the move time limit or a correspondence time control.
+    pub fn move_allowance(&self) -> Option<Duration> {
+        let correspondence = match self.time_control.mode {
+            ClockMode::Correspondence(limit) => Some(limit),
+            _ => None,
+        };
+        match (self.move_time_limit, correspondence) {
+            (Some(limit), Some(correspondence)) => Some(limit.min(correspondence)),
+            (limit, correspondence) => limit.or(correspondence),
+        }
+    }
+
+    /// Position the game starts from, the standard one if the FEN is invalid.
+    pub fn starting_position(&self) -> Position {
+        parse_fen(&self.starting_fen).unwrap_or_else(|err| {
+            warn!("invalid starting FEN {:?}: {}", self.starting_fen, err);
+            parse_fen(STARTING_FEN).unwrap()
+        })
+    }
+}
@@ -0,0 +1,170 @@
+use bevy::prelude::*;
+
+use crate::board::{IllegalMoveEvent, PlayerTurn, SelectedPiece};
+use crate::clock::{ChessClock, LOW_TIME};
+use crate::pieces::*;
+use crate::settings::Settings;
+
+use std::time::Duration;
+
+/// Sounds of the audio cues, insert it before the plugins to change them.
+/// Paths are relative to the `assets` folder, `None` silences a cue.
+#[derive(Clone)]
+pub struct AudioCues {
+    /// A piece is selected
+    pub select: Option<String>,
+    /// A move or a selection is refused
+    pub illegal_move: Option<String>,
+    /// The king of the player to move is attacked
+    pub check: Option<String>,
+    /// The next player can move, and is not in check
+    pub turn: Option<String>,
+    /// Each second of the player to move under the low time threshold, played
+    /// unless the clock is silenced rather than with the other cues
+    pub clock_tick: Option<String>,
+}
+
+impl Default for AudioCues {
+    fn default() -> Self {
+        Self {
+            select: Some("sounds/select.wav".to_string()),
+            illegal_move: Some("sounds/illegal.wav".to_string()),
+            check: Some("sounds/check.wav".to_string()),
+            turn: Some("sounds/turn.wav".to_string()),
+            clock_tick: Some("sounds/tick.wav".to_string()),
+        }
+    }
+}
+
+/// Loaded sounds of the audio cues.
+struct CueSounds {
+    select: Option<Handle<AudioSource>>,
+    illegal_move: Option<Handle<AudioSource>>,
+    check: Option<Handle<AudioSource>>,
+    turn: Option<Handle<AudioSource>>,
+    clock_tick: Option<Handle<AudioSource>>,
+}
+
+impl FromWorld for CueSounds {
+    fn from_world(world: &mut World) -> Self {
+        let cues = world
+            .get_resource_or_insert_with(AudioCues::default)
+            .clone();
+        // Inserted by DefaultPlugins, which the game plugins must be added after
+        let asset_server = world
+            .get_resource::<AssetServer>()
+            .expect("DefaultPlugins must be added before the game plugins");
+        let load =
+            |path: &Option<String>| path.as_ref().map(|path| asset_server.load(path.as_str()));
+        Self {
+            select: load(&cues.select),
+            illegal_move: load(&cues.illegal_move),
+            check: load(&cues.check),
+            turn: load(&cues.turn),
+            clock_tick: load(&cues.clock_tick),
+        }
+    }
+}
+
+/// Play a cue, unless it is silenced.
+fn play(audio: &Audio, sound: &Option<Handle<AudioSource>>) {
+    if let Some(sound) = sound {
+        audio.play(sound.clone());
+    }
+}
+
+/// Cue a new selection
+fn select_cue(
+    settings: Res<Settings>,
+    audio: Res<Audio>,
+    sounds: Res<CueSounds>,
+    selected_piece: Res<SelectedPiece>,
+    mut last_selected: Local<Option<Entity>>,
+) {
+    if !selected_piece.is_changed() || selected_piece.entity() == *last_selected {
+        return;
+    }
+    *last_selected = selected_piece.entity();
+    if settings.audio_cues && last_selected.is_some() {
+        play(&audio, &sounds.select);
+    }
+}
+
+/// Cue refused moves and selections
+fn illegal_move_cue(
+    settings: Res<Settings>,
+    audio: Res<Audio>,
+    sounds: Res<CueSounds>,
+    mut illegal_move_events: EventReader<IllegalMoveEvent>,
+) {
+    if illegal_move_events.iter().last().is_some() && settings.audio_cues {
+        play(&audio, &sounds.illegal_move);
+    }
+}
+
+/// Cue the start of each turn, as a check when the king of the player to move is attacked
+fn turn_cue(
+    settings: Res<Settings>,
+    audio: Res<Audio>,
+    sounds: Res<CueSounds>,
+    turn: Res<PlayerTurn>,
+    board: Res<BoardState>,
+) {
+    // The board state changes the frame after every move
+    if !board.is_changed() || !settings.audio_cues {
+        return;
+    }
+
+    let pieces = &board.pieces;
+    let check = pieces
+        .iter()
+        .filter(|piece| piece.piece_type == PieceType::King && piece.color == turn.0)
+        .any(|king| is_attacked((king.x, king.y), turn.0.opponent(), pieces));
+    play(&audio, if check { &sounds.check } else { &sounds.turn });
+}
+
+/// Tick every second while the clock of the player to move is in time scramble
+fn clock_tick(
+    settings: Res<Settings>,
+    audio: Res<Audio>,
+    sounds: Res<CueSounds>,
+    turn: Res<PlayerTurn>,
+    clock: Res<ChessClock>,
+    mut last_second: Local<Option<u64>>,
+) {
+    // The clock only runs while the game is played
+    if !clock.is_changed() {
+        return;
+    }
+
+    let remaining = clock.remaining(turn.0);
+    if remaining >= LOW_TIME || remaining == Duration::ZERO {
+        *last_second = None;
+        return;
+    }
+    let second = remaining.as_secs();
+    if *last_second != Some(second) {
+        *last_second = Some(second);
+        if !settings.silent_clock {
+            play(&audio, &sounds.clock_tick);
+        }
+    }
+}
+
+/// Cues Plugin
+///
+/// Plays a distinct sound on selection, refused moves, check and the start of each
+/// turn, so that the game can be followed by ear, when the *Audio cues* setting is
+/// on. The clock of the player to move also ticks every second of time scramble,
+/// unless the *Silent clock* setting is on.
+pub struct CuesPlugin;
+
+impl Plugin for CuesPlugin {
+    fn build(&self, app: &mut AppBuilder) {
+        app.init_resource::<CueSounds>()
+            .add_system(select_cue.system())
+            .add_system(illegal_move_cue.system())
+            .add_system(turn_cue.system())
+            .add_system(clock_tick.system());
+    }
+}
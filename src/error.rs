@@ -0,0 +1,40 @@
+use bevy::ecs::schedule::StateError;
+use bevy::prelude::*;
+
+use std::fmt;
+
+/// Unexpected error in a game system, reported to the players instead of panicking.
+#[derive(Debug)]
+pub enum ChessError {
+    /// An entity was despawned while a system still referred to it, e.g. a selected square.
+    MissingEntity(&'static str),
+    /// The app state could not be changed.
+    State(StateError),
+    /// The game assets could not be loaded, even from the default kit.
+    Assets(String),
+}
+
+impl fmt::Display for ChessError {
+    fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ChessError::MissingEntity(what) => write!(formatter, "missing {}", what),
+            ChessError::State(err) => write!(formatter, "cannot change state: {}", err),
+            ChessError::Assets(kit) => write!(formatter, "cannot load game assets from {}", kit),
+        }
+    }
+}
+
+impl From<StateError> for ChessError {
+    fn from(err: StateError) -> Self {
+        ChessError::State(err)
+    }
+}
+
+/// Send the error of a system, if any, for the status plugin to report.
+///
+/// Chained after systems returning a result: `system.system().chain(report_error.system())`.
+pub fn report_error(In(result): In<Result<(), ChessError>>, mut errors: EventWriter<ChessError>) {
+    if let Err(err) = result {
+        errors.send(err);
+    }
+}
@@ -0,0 +1,269 @@
+use crate::pieces::*;
+
+use std::fmt;
+
+/// FEN of the standard starting position.
+pub const STARTING_FEN: &str = "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1";
+
+/// Error parsing a FEN string.
+#[derive(Debug, Clone, PartialEq)]
+pub enum FenError {
+    /// The piece placement does not describe 8 ranks of 8 squares.
+    BadPlacement,
+    /// Unknown piece letter.
+    BadPiece(char),
+    /// Side to move is neither `w` nor `b`.
+    BadSideToMove,
+    /// Fullmove number is not a positive number.
+    BadMoveNumber,
+}
+
+impl fmt::Display for FenError {
+    fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            FenError::BadPlacement => write!(formatter, "invalid piece placement"),
+            FenError::BadPiece(c) => write!(formatter, "invalid piece '{}'", c),
+            FenError::BadSideToMove => write!(formatter, "invalid side to move"),
+            FenError::BadMoveNumber => write!(formatter, "invalid fullmove number"),
+        }
+    }
+}
+
+/// A position parsed from FEN.
+pub struct Position {
+    pub pieces: Vec<Piece>,
+    pub turn: PieceColor,
+    /// Number of the next full move, starting at 1
+    pub fullmove: usize,
+}
+
+/// Parse the piece placement, side to move and fullmove number of a FEN string.
+/// Castling, en passant and the halfmove clock are ignored as the game does not track them.
+pub fn parse_fen(fen: &str) -> Result<Position, FenError> {
+    let mut fields = fen.split_whitespace();
+    let placement = fields.next().ok_or(FenError::BadPlacement)?;
+
+    let ranks: Vec<&str> = placement.split('/').collect();
+    if ranks.len() != 8 {
+        return Err(FenError::BadPlacement);
+    }
+
+    let mut pieces = Vec::new();
+    // FEN lists ranks from the 8th down to the 1st
+    for (i, rank) in ranks.iter().enumerate() {
+        let x = 7 - i as u8;
+        let mut y = 0;
+        for c in rank.chars() {
+            if let Some(empty) = c.to_digit(10) {
+                // A run of empty squares is 1 to 8 and cannot pass the end of the rank,
+                // so a long run of digits cannot overflow
+                if empty == 0 || y + empty as u8 > 8 {
+                    return Err(FenError::BadPlacement);
+                }
+                y += empty as u8;
+            } else {
+                if y > 7 {
+                    return Err(FenError::BadPlacement);
+                }
+                let color = if c.is_ascii_uppercase() {
+                    PieceColor::White
+                } else {
+                    PieceColor::Black
+                };
+                let piece_type = match c.to_ascii_lowercase() {
+                    'k' => PieceType::King,
+                    'q' => PieceType::Queen,
+                    'b' => PieceType::Bishop,
+                    'n' => PieceType::Knight,
+                    'r' => PieceType::Rook,
+                    'p' => PieceType::Pawn,
+                    _ => return Err(FenError::BadPiece(c)),
+                };
+                pieces.push(Piece {
+                    color,
+                    piece_type,
+                    x,
+                    y,
+                });
+                y += 1;
+            }
+        }
+        if y != 8 {
+            return Err(FenError::BadPlacement);
+        }
+    }
+
+    let turn = match fields.next() {
+        None | Some("w") => PieceColor::White,
+        Some("b") => PieceColor::Black,
+        Some(_) => return Err(FenError::BadSideToMove),
+    };
+
+    // Skip castling, en passant and the halfmove clock
+    let fullmove = match fields.nth(3) {
+        None => 1,
+        Some(number) => match number.parse() {
+            Ok(number) if number > 0 => number,
+            _ => return Err(FenError::BadMoveNumber),
+        },
+    };
+
+    Ok(Position {
+        pieces,
+        turn,
+        fullmove,
+    })
+}
+
+/// Write the FEN of a position.
+/// Castling and en passant are not tracked by the game, so they are always empty.
+pub fn to_fen(pieces: &[Piece], turn: PieceColor, fullmove: usize) -> String {
+    let mut fen = String::new();
+    for x in (0..8).rev() {
+        let mut empty = 0;
+        for y in 0..8 {
+            match pieces.iter().find(|p| p.x == x && p.y == y) {
+                Some(piece) => {
+                    if empty > 0 {
+                        fen.push_str(&empty.to_string());
+                        empty = 0;
+                    }
+                    let letter = match piece.piece_type {
+                        PieceType::Pawn => "P",
+                        piece_type => piece_type.notation(),
+                    };
+                    match piece.color {
+                        PieceColor::White => fen.push_str(letter),
+                        PieceColor::Black => fen.push_str(&letter.to_ascii_lowercase()),
+                    }
+                }
+                None => empty += 1,
+            }
+        }
+        if empty > 0 {
+            fen.push_str(&empty.to_string());
+        }
+        if x > 0 {
+            fen.push('/');
+        }
+    }
+
+    let turn = match turn {
+        PieceColor::White => "w",
+        PieceColor::Black => "b",
+    };
+    format!("{} {} - - 0 {}", fen, turn, fullmove)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn placement_error(placement: &str) -> Option<FenError> {
+        parse_fen(&format!("{} w - - 0 1", placement)).err()
+    }
+
+    #[test]
+    fn round_trip() {
+        let fens = [
+            "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w - - 0 1",
+            "r1bqk2r/pppp1ppp/2n2n2/2b1p3/2B1P3/2N2N2/PPPP1PPP/R1BQK2R b - - 0 5",
+            "8/8/8/4k3/8/8/8/R3K3 w - - 0 60",
+        ];
+        for fen in fens.iter() {
+            let position = parse_fen(fen).unwrap();
+            assert_eq!(
+                &to_fen(&position.pieces, position.turn, position.fullmove),
+                fen
+            );
+        }
+    }
+
+    #[test]
+    fn starting_position() {
+        let position = parse_fen(STARTING_FEN).unwrap();
+        assert_eq!(position.pieces.len(), 32);
+        assert!(position.turn == PieceColor::White);
+        assert_eq!(position.fullmove, 1);
+        let king = position
+            .pieces
+            .iter()
+            .find(|p| p.piece_type == PieceType::King && p.color == PieceColor::White)
+            .unwrap();
+        assert_eq!((king.x, king.y), (0, 4));
+    }
+
+    #[test]
+    fn rejects_wrong_rank_count() {
+        assert_eq!(
+            placement_error("8/8/8/8/8/8/8"),
+            Some(FenError::BadPlacement)
+        );
+        assert_eq!(
+            placement_error("8/8/8/8/8/8/8/8/8"),
+            Some(FenError::BadPlacement)
+        );
+        assert_eq!(parse_fen("").err(), Some(FenError::BadPlacement));
+    }
+
+    #[test]
+    fn rejects_wrong_rank_length() {
+        assert_eq!(
+            placement_error("7/8/8/8/8/8/8/8"),
+            Some(FenError::BadPlacement)
+        );
+        assert_eq!(
+            placement_error("8p/8/8/8/8/8/8/8"),
+            Some(FenError::BadPlacement)
+        );
+        assert_eq!(
+            placement_error("ppppppppp/8/8/8/8/8/8/8"),
+            Some(FenError::BadPlacement)
+        );
+    }
+
+    #[test]
+    fn rejects_empty_runs_outside_1_to_8() {
+        assert_eq!(
+            placement_error("9/8/8/8/8/8/8/8"),
+            Some(FenError::BadPlacement)
+        );
+        assert_eq!(
+            placement_error("08/8/8/8/8/8/8/8"),
+            Some(FenError::BadPlacement)
+        );
+        assert_eq!(
+            placement_error("99999999999999999999999999999/8/8/8/8/8/8/8"),
+            Some(FenError::BadPlacement)
+        );
+    }
+
+    #[test]
+    fn rejects_bad_piece_and_side_to_move() {
+        assert_eq!(
+            placement_error("x7/8/8/8/8/8/8/8"),
+            Some(FenError::BadPiece('x'))
+        );
+        assert_eq!(
+            parse_fen("8/8/8/8/8/8/8/8 x").err(),
+            Some(FenError::BadSideToMove)
+        );
+    }
+
+    #[test]
+    fn reads_fullmove_number() {
+        assert_eq!(
+            parse_fen("8/8/8/8/8/8/8/8 b - - 0 12").unwrap().fullmove,
+            12
+        );
+        assert_eq!(parse_fen("8/8/8/8/8/8/8/8 w").unwrap().fullmove, 1);
+        assert_eq!(
+            parse_fen("8/8/8/8/8/8/8/8 w - - 0 0").err(),
+            Some(FenError::BadMoveNumber)
+        );
+        assert_eq!(
+            parse_fen("8/8/8/8/8/8/8/8 w - - 0 x").err(),
+            Some(FenError::BadMoveNumber)
+        );
+    }
+}
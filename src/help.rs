@@ -0,0 +1,123 @@
+use bevy::prelude::*;
+
+use crate::config::GameConfig;
+use crate::locale::*;
+use crate::settings::{KeyBindings, Settings};
+use crate::ui::UiAssets;
+
+/// Root of the help overlay
+struct HelpOverlay;
+
+/// Lines describing the rules of the current game, e.g. the time control
+fn rule_lines(language: Language, config: &GameConfig, settings: &Settings) -> Vec<String> {
+    let time_control = config.time_control.to_string();
+    let mut lines = vec![tr_with(language, "help_time_control", &time_control)];
+    if let Some(limit) = config.move_time_limit {
+        let limit = format!("{} s", limit.as_secs_f32().round());
+        lines.push(tr_with(language, "help_move_limit", &limit));
+    }
+    lines.push(format!(
+        "{}: {}",
+        tr(language, "confirm_moves"),
+        tr(language, if settings.confirm_moves { "on" } else { "off" }),
+    ));
+    lines
+}
+
+/// Title of a section of the help
+fn spawn_heading(parent: &mut ChildBuilder, assets: &UiAssets, title: &str) {
+    parent.spawn_bundle(TextBundle {
+        style: Style {
+            margin: Rect {
+                top: Val::Px(8.0),
+                bottom: Val::Px(4.0),
+                ..Default::default()
+            },
+            ..Default::default()
+        },
+        text: assets.text(title, 30.0),
+        ..Default::default()
+    });
+}
+
+/// Show or hide the help overlay
+fn toggle_help(
+    mut commands: Commands,
+    keys: Res<Input<KeyCode>>,
+    bindings: Res<KeyBindings>,
+    assets: Res<UiAssets>,
+    settings: Res<Settings>,
+    config: Res<GameConfig>,
+    overlay_query: Query<Entity, With<HelpOverlay>>,
+) {
+    let opened = overlay_query.iter().next();
+    let close = opened.is_some() && keys.just_pressed(bindings.cancel);
+    if !keys.just_pressed(bindings.help) && !close {
+        return;
+    }
+
+    // Close the overlay if already opened
+    if let Some(overlay) = opened {
+        commands.entity(overlay).despawn_recursive();
+        return;
+    }
+
+    let language = settings.language;
+    commands
+        .spawn_bundle(NodeBundle {
+            style: Style {
+                size: Size::new(Val::Percent(100.0), Val::Percent(100.0)),
+                position_type: PositionType::Absolute,
+                justify_content: JustifyContent::Center,
+                align_items: AlignItems::Center,
+                ..Default::default()
+            },
+            material: assets.transparent.clone(),
+            ..Default::default()
+        })
+        .insert(HelpOverlay)
+        .with_children(|parent| {
+            parent
+                .spawn_bundle(NodeBundle {
+                    style: Style {
+                        // Reversed as UI nodes are laid out from the bottom
+                        flex_direction: FlexDirection::ColumnReverse,
+                        align_items: AlignItems::FlexStart,
+                        padding: Rect::all(Val::Px(16.0)),
+                        ..Default::default()
+                    },
+                    material: assets.panel.clone(),
+                    ..Default::default()
+                })
+                .with_children(|parent| {
+                    spawn_heading(parent, &assets, tr(language, "help_controls"));
+                    for (keys, action) in bindings.controls() {
+                        let line = format!("{}: {}", keys, tr(language, action));
+                        parent.spawn_bundle(TextBundle {
+                            text: assets.text(&line, 22.0),
+                            ..Default::default()
+                        });
+                    }
+
+                    spawn_heading(parent, &assets, tr(language, "help_rules"));
+                    for line in rule_lines(language, &config, &settings) {
+                        parent.spawn_bundle(TextBundle {
+                            text: assets.text(&line, 22.0),
+                            ..Default::default()
+                        });
+                    }
+                });
+        });
+}
+
+/// Help Plugin
+///
+/// Lists the controls and the rules of the current game over the board, from
+/// the key bindings and the game configuration.
+pub struct HelpPlugin;
+
+impl Plugin for HelpPlugin {
+    fn build(&self, app: &mut AppBuilder) {
+        app.add_system(toggle_help.system());
+    }
+}
@@ -0,0 +1,168 @@
+use bevy::prelude::*;
+use bevy::transform::TransformSystem;
+use bevy::utils::HashMap;
+
+use crate::assets::ChessAssets;
+use crate::settings::Settings;
+
+/// Kind of square highlight. Layers are drawn over each other in `ALL` order,
+/// so several can show on the same square.
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub enum HighlightLayer {
+    /// Squares of the last move
+    LastMove,
+    /// King of the player to move while it is attacked
+    Check,
+    /// Pieces of the player to move that are attacked and not defended
+    Threats,
+    /// Square under the mouse cursor
+    Hovered,
+    /// Selected square
+    Selected,
+    /// Destinations of the selected piece
+    LegalMoves,
+    /// Ends of the arrow of the pending move
+    ArrowAnchors,
+    /// Square under the keyboard cursor
+    Cursor,
+}
+
+impl HighlightLayer {
+    /// All layers, from the lowest to the highest.
+    pub const ALL: &'static [HighlightLayer] = &[
+        HighlightLayer::LastMove,
+        HighlightLayer::Check,
+        HighlightLayer::Threats,
+        HighlightLayer::Hovered,
+        HighlightLayer::Selected,
+        HighlightLayer::LegalMoves,
+        HighlightLayer::ArrowAnchors,
+        HighlightLayer::Cursor,
+    ];
+
+    fn material(self, assets: &ChessAssets) -> &Handle<StandardMaterial> {
+        match self {
+            HighlightLayer::LastMove => &assets.last_move_marker,
+            HighlightLayer::Check => &assets.check_marker,
+            HighlightLayer::Threats => &assets.threat_marker,
+            HighlightLayer::Hovered | HighlightLayer::Cursor => &assets.hovered_square,
+            HighlightLayer::Selected => &assets.selected_square,
+            HighlightLayer::LegalMoves => &assets.legal_move_marker,
+            HighlightLayer::ArrowAnchors => &assets.pending_arrow,
+        }
+    }
+
+    /// Size of the highlight, relative to the square.
+    fn scale(self) -> f32 {
+        match self {
+            // Leaving the border of the square visible
+            HighlightLayer::Threats => 0.8,
+            HighlightLayer::LegalMoves => 0.3,
+            HighlightLayer::ArrowAnchors => 0.4,
+            HighlightLayer::Cursor => 0.6,
+            _ => 1.0,
+        }
+    }
+}
+
+/// Highlighted squares of each layer, drawn with a pool of quads over the board.
+#[derive(Default)]
+pub struct Highlights {
+    layers: HashMap<HighlightLayer, Vec<(u8, u8)>>,
+}
+
+impl Highlights {
+    /// Replace the squares of a layer, reusing its buffer.
+    pub fn set(&mut self, layer: HighlightLayer, squares: impl IntoIterator<Item = (u8, u8)>) {
+        let layer = self.layers.entry(layer).or_default();
+        layer.clear();
+        layer.extend(squares);
+    }
+
+    /// Remove the squares of a layer.
+    pub fn clear(&mut self, layer: HighlightLayer) {
+        self.set(layer, None);
+    }
+
+    /// Squares of a layer.
+    pub fn squares(&self, layer: HighlightLayer) -> &[(u8, u8)] {
+        self.layers.get(&layer).map(Vec::as_slice).unwrap_or(&[])
+    }
+}
+
+/// Quad of the highlight pool, hidden while unused
+struct HighlightQuad;
+
+/// Move the quads of the pool over the highlighted squares, spawning more when needed
+fn draw_highlights(
+    mut commands: Commands,
+    assets: Res<ChessAssets>,
+    settings: Res<Settings>,
+    highlights: Res<Highlights>,
+    mut quad_query: Query<
+        (&mut Transform, &mut Handle<StandardMaterial>, &mut Visible),
+        With<HighlightQuad>,
+    >,
+) {
+    if !highlights.is_changed() && !settings.is_changed() {
+        return;
+    }
+
+    let size = settings.board_gap.square_size();
+    let mut quads = quad_query.iter_mut();
+    for (i, &layer) in HighlightLayer::ALL.iter().enumerate() {
+        let material = layer.material(&assets);
+        let scale = layer.scale() * size;
+        for &(x, y) in highlights.squares(layer) {
+            let transform = Transform {
+                // Higher layers just above lower ones
+                translation: Vec3::new(x as f32, 0.004 + 0.001 * i as f32, y as f32),
+                scale: Vec3::new(scale, 1.0, scale),
+                ..Default::default()
+            };
+            match quads.next() {
+                Some((mut quad_transform, mut quad_material, mut visible)) => {
+                    *quad_transform = transform;
+                    *quad_material = material.clone();
+                    visible.is_visible = true;
+                }
+                None => {
+                    commands
+                        .spawn_bundle(PbrBundle {
+                            mesh: assets.square_mesh.clone(),
+                            material: material.clone(),
+                            transform,
+                            visible: Visible {
+                                is_visible: true,
+                                is_transparent: true,
+                            },
+                            ..Default::default()
+                        })
+                        .insert(HighlightQuad);
+                }
+            }
+        }
+    }
+
+    // Unused quads stay in the pool
+    for (_, _, mut visible) in quads {
+        visible.is_visible = false;
+    }
+}
+
+/// Highlight Plugin
+///
+/// Draws the squares of each highlight layer with a pool of translucent quads
+/// over the board, instead of changing the materials of the squares.
+pub struct HighlightPlugin;
+
+impl Plugin for HighlightPlugin {
+    fn build(&self, app: &mut AppBuilder) {
+        app.init_resource::<Highlights>().add_system_to_stage(
+            CoreStage::PostUpdate,
+            draw_highlights
+                .system()
+                .before(TransformSystem::TransformPropagate),
+        );
+    }
+}
@@ -0,0 +1,413 @@
+use bevy::prelude::*;
+
+use crate::board::*;
+use crate::fen::Position;
+use crate::pieces::*;
+use crate::settings::NotationStyle;
+
+use std::fmt;
+
+/// Annotation glyph of a move (NAG).
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Nag {
+    Good,
+    Mistake,
+    Brilliant,
+    Blunder,
+    Interesting,
+    Dubious,
+}
+
+impl Nag {
+    /// Numeric code of the glyph, as written in PGN (`$1`, `$2`, ...).
+    pub fn code(self) -> u8 {
+        match self {
+            Nag::Good => 1,
+            Nag::Mistake => 2,
+            Nag::Brilliant => 3,
+            Nag::Blunder => 4,
+            Nag::Interesting => 5,
+            Nag::Dubious => 6,
+        }
+    }
+}
+
+impl fmt::Display for Nag {
+    fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            formatter,
+            "{}",
+            match self {
+                Nag::Good => "!",
+                Nag::Mistake => "?",
+                Nag::Brilliant => "!!",
+                Nag::Blunder => "??",
+                Nag::Interesting => "!?",
+                Nag::Dubious => "?!",
+            }
+        )
+    }
+}
+
+/// A move played in the game.
+pub struct MoveRecord {
+    /// The moved piece, before the move
+    pub piece: Piece,
+    /// Destination square
+    pub to: (u8, u8),
+    /// Piece taken by the move
+    pub captured: Option<Piece>,
+    /// Standard algebraic notation of the move
+    pub san: String,
+    /// Annotation glyph
+    pub nag: Option<Nag>,
+    /// Free text comment
+    pub comment: Option<String>,
+}
+
+impl MoveRecord {
+    /// Write the move with the given piece letters, e.g. to localize them.
+    pub fn write_with(
+        &self,
+        formatter: &mut impl fmt::Write,
+        letter: impl Fn(PieceType) -> &'static str,
+    ) -> fmt::Result {
+        self.write_styled(formatter, NotationStyle::Long, letter)
+    }
+
+    /// Write the move in the given notation style, with the given piece letters.
+    /// Figurine notation ignores the letters.
+    pub fn write_styled(
+        &self,
+        formatter: &mut impl fmt::Write,
+        style: NotationStyle,
+        letter: impl Fn(PieceType) -> &'static str,
+    ) -> fmt::Result {
+        match style {
+            // Long algebraic notation, e.g. "Ng1-f3" or "e4xd5"
+            NotationStyle::Long => write!(
+                formatter,
+                "{}{}{}{}",
+                letter(self.piece.piece_type),
+                square_name((self.piece.x, self.piece.y)),
+                if self.captured.is_some() { "x" } else { "-" },
+                square_name(self.to),
+            )?,
+            // The recorded SAN starts with the English letter of the piece
+            NotationStyle::San | NotationStyle::Figurine => {
+                let piece_type = self.piece.piece_type;
+                let prefix = if style == NotationStyle::Figurine {
+                    piece_type.figurine()
+                } else {
+                    letter(piece_type)
+                };
+                let rest = self
+                    .san
+                    .get(piece_type.notation().len()..)
+                    .unwrap_or(&self.san);
+                write!(formatter, "{}{}", prefix, rest)?;
+            }
+        }
+        if let Some(nag) = self.nag {
+            write!(formatter, "{}", nag)?;
+        }
+        if let Some(comment) = &self.comment {
+            write!(formatter, " {{{}}}", comment)?;
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Display for MoveRecord {
+    fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        self.write_with(formatter, PieceType::notation)
+    }
+}
+
+/// Standard algebraic notation of a move, given the pieces before the move.
+/// Castling and promotion are not played by the game, so they are not written.
+pub fn san(piece: &Piece, to: (u8, u8), pieces: &Vec<Piece>) -> String {
+    let from = (piece.x, piece.y);
+    let capture = pieces.iter().any(|p| (p.x, p.y) == to);
+    let mut san = String::new();
+
+    if piece.piece_type == PieceType::Pawn {
+        if capture {
+            san.push_str(&square_name(from)[..1]);
+        }
+    } else {
+        san.push_str(piece.piece_type.notation());
+        // Disambiguate from other pieces of the same kind reaching the square
+        let others: Vec<&Piece> = pieces
+            .iter()
+            .filter(|p| {
+                p.color == piece.color
+                    && p.piece_type == piece.piece_type
+                    && (p.x, p.y) != from
+                    && p.is_move_valid(to, pieces)
+            })
+            .collect();
+        if !others.is_empty() {
+            let name = square_name(from);
+            if others.iter().all(|p| p.y != piece.y) {
+                san.push_str(&name[..1]);
+            } else if others.iter().all(|p| p.x != piece.x) {
+                san.push_str(&name[1..]);
+            } else {
+                san.push_str(&name);
+            }
+        }
+    }
+    if capture {
+        san.push('x');
+    }
+    san.push_str(&square_name(to));
+
+    // Check, if the moved side now attacks the opponent king
+    let after: Vec<Piece> = pieces
+        .iter()
+        .filter(|p| (p.x, p.y) != to)
+        .map(|p| {
+            let mut p = *p;
+            if (p.x, p.y) == from {
+                p.x = to.0;
+                p.y = to.1;
+            }
+            p
+        })
+        .collect();
+    let king = after
+        .iter()
+        .find(|p| p.piece_type == PieceType::King && p.color != piece.color);
+    if let Some(king) = king {
+        if after
+            .iter()
+            .any(|p| p.color == piece.color && p.is_move_valid((king.x, king.y), &after))
+        {
+            san.push('+');
+        }
+    }
+    san
+}
+
+/// Moves played since the position was set up.
+pub struct MoveHistory {
+    pub moves: Vec<MoveRecord>,
+    /// Fullmove number of the position the moves start from
+    pub first_move: usize,
+    /// Side to move in the position the moves start from
+    pub first_turn: PieceColor,
+}
+
+impl Default for MoveHistory {
+    fn default() -> Self {
+        Self {
+            moves: Vec::new(),
+            first_move: 1,
+            first_turn: PieceColor::White,
+        }
+    }
+}
+
+impl MoveHistory {
+    /// Forget all moves and start counting from the given position.
+    pub fn start_from(&mut self, position: &Position) {
+        self.moves.clear();
+        self.first_move = position.fullmove;
+        self.first_turn = position.turn;
+    }
+
+    /// Number of the next full move, for the FEN of the current position.
+    pub fn fullmove(&self) -> usize {
+        self.move_number(self.moves.len())
+    }
+
+    /// Full move number of the move at the given index of the history.
+    pub fn move_number(&self, index: usize) -> usize {
+        // A full move ends with a move of Black
+        let plies = match self.first_turn {
+            PieceColor::White => index,
+            PieceColor::Black => index + 1,
+        };
+        self.first_move + plies / 2
+    }
+
+    /// Move list in standard algebraic notation with its annotations, as in PGN,
+    /// e.g. "1. e4 e5 2. Nf3 $1 {Develops}".
+    pub fn movetext(&self) -> String {
+        let mut movetext = String::new();
+        for (i, record) in self.moves.iter().enumerate() {
+            if i > 0 {
+                movetext.push(' ');
+            }
+            if record.piece.color == PieceColor::White || i == 0 {
+                movetext.push_str(&self.move_number(i).to_string());
+                movetext.push_str(if record.piece.color == PieceColor::White {
+                    ". "
+                } else {
+                    "... "
+                });
+            }
+            movetext.push_str(&record.san);
+            if let Some(nag) = record.nag {
+                movetext.push_str(&format!(" ${}", nag.code()));
+            }
+            if let Some(comment) = &record.comment {
+                // PGN comments end at the first closing brace
+                movetext.push_str(&format!(" {{{}}}", comment.replace('}', ")")));
+            }
+        }
+        movetext
+    }
+}
+
+/// Event setting the annotation of a move in the history.
+pub struct AnnotateMoveEvent {
+    /// Index of the move in the history
+    pub index: usize,
+    pub nag: Option<Nag>,
+    pub comment: Option<String>,
+}
+
+/// History Plugin
+pub struct HistoryPlugin;
+
+impl Plugin for HistoryPlugin {
+    fn build(&self, app: &mut AppBuilder) {
+        app.init_resource::<MoveHistory>()
+            .add_event::<AnnotateMoveEvent>()
+            .add_system(clear_history.system().label("clear_history"))
+            .add_system(
+                record_moves
+                    .system()
+                    .label("record_moves")
+                    .after("clear_history"),
+            )
+            .add_system(annotate_moves.system().after("record_moves"));
+    }
+}
+
+/// Forget all moves when a new position is loaded.
+fn clear_history(
+    mut history: ResMut<MoveHistory>,
+    mut load_position_events: EventReader<LoadPositionEvent>,
+) {
+    if let Some(event) = load_position_events.iter().last() {
+        history.start_from(&event.position);
+    }
+}
+
+/// Append moves to the history.
+fn record_moves(mut history: ResMut<MoveHistory>, mut move_events: EventReader<MoveEvent>) {
+    for event in move_events.iter() {
+        history.moves.push(MoveRecord {
+            piece: event.piece,
+            to: event.to,
+            captured: event.captured,
+            san: event.san.clone(),
+            nag: None,
+            comment: None,
+        });
+    }
+}
+
+/// Attach annotations to moves of the history.
+fn annotate_moves(
+    mut history: ResMut<MoveHistory>,
+    mut annotate_move_events: EventReader<AnnotateMoveEvent>,
+) {
+    for event in annotate_move_events.iter() {
+        if let Some(record) = history.moves.get_mut(event.index) {
+            record.nag = event.nag;
+            record.comment = event.comment.clone();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fen::{parse_fen, STARTING_FEN};
+
+    fn piece_at(pieces: &[Piece], square: (u8, u8)) -> Piece {
+        *pieces.iter().find(|p| (p.x, p.y) == square).unwrap()
+    }
+
+    fn record(piece: Piece, to: (u8, u8), san: &str) -> MoveRecord {
+        MoveRecord {
+            piece,
+            to,
+            captured: None,
+            san: san.to_string(),
+            nag: None,
+            comment: None,
+        }
+    }
+
+    #[test]
+    fn san_of_simple_moves() {
+        let pieces = parse_fen(STARTING_FEN).unwrap().pieces;
+        assert_eq!(san(&piece_at(&pieces, (1, 4)), (3, 4), &pieces), "e4");
+        assert_eq!(san(&piece_at(&pieces, (0, 6)), (2, 5), &pieces), "Nf3");
+    }
+
+    #[test]
+    fn san_of_captures_and_checks() {
+        let pieces = parse_fen("4k3/8/8/3p4/4P3/8/8/4K2R w - - 0 1")
+            .unwrap()
+            .pieces;
+        assert_eq!(san(&piece_at(&pieces, (3, 4)), (4, 3), &pieces), "exd5");
+        assert_eq!(san(&piece_at(&pieces, (0, 7)), (7, 7), &pieces), "Rh8+");
+    }
+
+    #[test]
+    fn san_disambiguates_pieces_reaching_the_same_square() {
+        let pieces = parse_fen("4k3/8/8/8/8/8/8/R6R w - - 0 1").unwrap().pieces;
+        assert_eq!(san(&piece_at(&pieces, (0, 0)), (0, 3), &pieces), "Rad1");
+        let pieces = parse_fen("4k3/R7/8/8/8/8/8/R3K3 w - - 0 1").unwrap().pieces;
+        assert_eq!(san(&piece_at(&pieces, (0, 0)), (3, 0), &pieces), "R1a4");
+    }
+
+    #[test]
+    fn movetext_numbers_moves_from_the_starting_position() {
+        let pieces = parse_fen(STARTING_FEN).unwrap().pieces;
+        let mut history = MoveHistory::default();
+        history
+            .moves
+            .push(record(piece_at(&pieces, (1, 4)), (3, 4), "e4"));
+        history
+            .moves
+            .push(record(piece_at(&pieces, (6, 4)), (4, 4), "e5"));
+        history
+            .moves
+            .push(record(piece_at(&pieces, (0, 6)), (2, 5), "Nf3"));
+        assert_eq!(history.movetext(), "1. e4 e5 2. Nf3");
+
+        let position = parse_fen("4k3/8/8/8/8/8/4p3/4K3 b - - 0 40").unwrap();
+        history.start_from(&position);
+        history
+            .moves
+            .push(record(piece_at(&position.pieces, (7, 4)), (6, 3), "Kd7"));
+        history
+            .moves
+            .push(record(piece_at(&position.pieces, (0, 4)), (1, 4), "Kxe2"));
+        assert_eq!(history.movetext(), "40... Kd7 41. Kxe2");
+        assert_eq!(history.move_number(0), 40);
+        assert_eq!(history.move_number(1), 41);
+        assert_eq!(history.fullmove(), 41);
+    }
+
+    #[test]
+    fn movetext_writes_annotations() {
+        let pieces = parse_fen(STARTING_FEN).unwrap().pieces;
+        let mut history = MoveHistory::default();
+        let mut e4 = record(piece_at(&pieces, (1, 4)), (3, 4), "e4");
+        e4.nag = Some(Nag::Good);
+        e4.comment = Some("Best by test}".to_string());
+        history.moves.push(e4);
+        history
+            .moves
+            .push(record(piece_at(&pieces, (6, 4)), (4, 4), "e5"));
+        assert_eq!(history.movetext(), "1. e4 $1 {Best by test)} e5");
+    }
+}
@@ -0,0 +1,179 @@
+use bevy::asset::{AssetLoader, LoadContext, LoadedAsset};
+use bevy::prelude::*;
+use bevy::reflect::TypeUuid;
+use bevy::utils::BoxedFuture;
+use serde::Deserialize;
+
+use crate::pieces::PieceType;
+
+use std::fmt;
+
+/// Kit loaded when no other is configured.
+pub const DEFAULT_KIT: &str = "models/chess_kit/kit.ron";
+
+/// Piece kit to load, insert it before the plugins to use alternative models.
+pub struct KitConfig {
+    pub path: String,
+}
+
+impl Default for KitConfig {
+    fn default() -> Self {
+        Self {
+            path: DEFAULT_KIT.to_string(),
+        }
+    }
+}
+
+/// Model of a piece in a kit.
+#[derive(Debug, Deserialize)]
+pub struct PieceModel {
+    /// Labels of the meshes in the kit file, e.g. "Mesh0/Primitive0"
+    pub meshes: Vec<String>,
+    /// Offset of the meshes from the center of the square
+    pub offset: (f32, f32, f32),
+}
+
+/// Set of piece models, described in a RON file.
+#[derive(Debug, Deserialize, TypeUuid)]
+#[uuid = "0f3a5c4e-5f0b-4d0c-9a57-3c8e2b1d7a61"]
+pub struct PieceKit {
+    /// Path of the glTF file holding the meshes
+    pub file: String,
+    /// Scale applied to the meshes
+    pub scale: f32,
+    pub king: PieceModel,
+    pub queen: PieceModel,
+    pub bishop: PieceModel,
+    pub knight: PieceModel,
+    pub rook: PieceModel,
+    pub pawn: PieceModel,
+}
+
+/// Error in the description of a piece kit.
+#[derive(Debug, Clone, PartialEq)]
+pub enum KitError {
+    /// The file holding the meshes is not a glTF file.
+    NotGltf(String),
+    /// The scale is not a positive number.
+    BadScale(f32),
+    /// A piece has no mesh.
+    NoMesh(PieceType),
+}
+
+impl fmt::Display for KitError {
+    fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            KitError::NotGltf(file) => write!(formatter, "{} is not a .glb or .gltf file", file),
+            KitError::BadScale(scale) => write!(formatter, "invalid scale {}", scale),
+            KitError::NoMesh(piece_type) => write!(formatter, "no mesh for {:?}", piece_type),
+        }
+    }
+}
+
+impl std::error::Error for KitError {}
+
+impl PieceKit {
+    /// Check the kit before loading its meshes. Mesh labels are only checked once the
+    /// glTF file is loaded.
+    pub fn validate(&self) -> Result<(), KitError> {
+        if !self.file.ends_with(".glb") && !self.file.ends_with(".gltf") {
+            return Err(KitError::NotGltf(self.file.clone()));
+        }
+        if !self.scale.is_finite() || self.scale <= 0.0 {
+            return Err(KitError::BadScale(self.scale));
+        }
+        let piece_types = [
+            PieceType::King,
+            PieceType::Queen,
+            PieceType::Bishop,
+            PieceType::Knight,
+            PieceType::Rook,
+            PieceType::Pawn,
+        ];
+        for &piece_type in piece_types.iter() {
+            if self.model(piece_type).meshes.is_empty() {
+                return Err(KitError::NoMesh(piece_type));
+            }
+        }
+        Ok(())
+    }
+
+    /// Model of a piece type.
+    pub fn model(&self, piece_type: PieceType) -> &PieceModel {
+        match piece_type {
+            PieceType::King => &self.king,
+            PieceType::Queen => &self.queen,
+            PieceType::Bishop => &self.bishop,
+            PieceType::Knight => &self.knight,
+            PieceType::Rook => &self.rook,
+            PieceType::Pawn => &self.pawn,
+        }
+    }
+}
+
+/// Loader of `.ron` piece kits.
+#[derive(Default)]
+pub struct PieceKitLoader;
+
+impl AssetLoader for PieceKitLoader {
+    fn load<'a>(
+        &'a self,
+        bytes: &'a [u8],
+        load_context: &'a mut LoadContext,
+    ) -> BoxedFuture<'a, Result<(), anyhow::Error>> {
+        Box::pin(async move {
+            let kit = ron::de::from_bytes::<PieceKit>(bytes)?;
+            kit.validate()?;
+            load_context.set_default_asset(LoadedAsset::new(kit));
+            Ok(())
+        })
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["ron"]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn default_kit() -> PieceKit {
+        ron::de::from_str(include_str!("../assets/models/chess_kit/kit.ron")).unwrap()
+    }
+
+    #[test]
+    fn default_kit_is_valid() {
+        assert_eq!(default_kit().validate(), Ok(()));
+    }
+
+    #[test]
+    fn rejects_other_files() {
+        let mut kit = default_kit();
+        kit.file = "models/pieces.obj".to_string();
+        assert_eq!(
+            kit.validate(),
+            Err(KitError::NotGltf("models/pieces.obj".to_string()))
+        );
+    }
+
+    #[test]
+    fn rejects_bad_scales() {
+        for &scale in [0.0, -1.0, f32::INFINITY].iter() {
+            let mut kit = default_kit();
+            kit.scale = scale;
+            assert_eq!(kit.validate(), Err(KitError::BadScale(scale)));
+        }
+        // NaN is not equal to itself
+        let mut kit = default_kit();
+        kit.scale = f32::NAN;
+        assert!(matches!(kit.validate(), Err(KitError::BadScale(_))));
+    }
+
+    #[test]
+    fn rejects_pieces_without_mesh() {
+        let mut kit = default_kit();
+        kit.knight.meshes.clear();
+        assert_eq!(kit.validate(), Err(KitError::NoMesh(PieceType::Knight)));
+    }
+}
@@ -0,0 +1,381 @@
+use bevy::prelude::*;
+use bevy::render::camera::PerspectiveProjection;
+use bevy::window::WindowResized;
+
+use crate::board::{square_name, MoveEvent, PlayerTurn, Taken};
+use crate::locale::*;
+use crate::pieces::*;
+use crate::settings::Settings;
+use crate::ui::UiAssets;
+
+use std::collections::HashSet;
+
+/// Height of the labels above the pieces.
+const LABEL_HEIGHT: f32 = 1.2;
+/// Horizontal offset roughly centering a label on its anchor, in pixels.
+const LABEL_OFFSET: f32 = -14.0;
+/// Height of the move count badges above the pieces, clear of the labels.
+const BADGE_HEIGHT: f32 = 1.6;
+const BADGE_COLOR: Color = Color::rgb(1.0, 0.85, 0.2);
+/// Time a move callout floats above its square, in seconds.
+const CALLOUT_DURATION: f32 = 1.5;
+/// Part of the callout duration spent fading out.
+const CALLOUT_FADE: f32 = 0.4;
+/// Heights between which a move callout rises.
+const CALLOUT_START_HEIGHT: f32 = 1.0;
+const CALLOUT_END_HEIGHT: f32 = 2.0;
+
+/// Label floating above a piece
+struct PieceLabel(Entity);
+
+/// Number of moves a piece of the player to move can play
+struct MoveCountBadge(Entity);
+
+/// Notation of the last move, rising above its destination square before fading
+struct MoveCallout {
+    anchor: Vec3,
+    timer: Timer,
+}
+
+/// Camera of the board, changed when it moves or its projection follows the window
+type MovedCamera = (
+    With<PerspectiveProjection>,
+    Or<(Changed<Camera>, Changed<GlobalTransform>)>,
+);
+
+/// Place a node at a screen position, or hide it when its anchor is off screen.
+/// Style and visibility are only written when they differ, so an idle node is not laid out again.
+fn place_node(style: &mut Mut<Style>, visible: &mut Mut<Visible>, position: Option<Vec2>) {
+    match position {
+        Some(position) => {
+            let (left, bottom) = (Val::Px(position.x), Val::Px(position.y));
+            if style.position.left != left || style.position.bottom != bottom {
+                style.position.left = left;
+                style.position.bottom = bottom;
+            }
+            if !visible.is_visible {
+                visible.is_visible = true;
+            }
+        }
+        None => {
+            if visible.is_visible {
+                visible.is_visible = false;
+            }
+        }
+    }
+}
+
+/// Text of the label of a piece, e.g. "Ng1" or "e2"
+fn label_text(piece: &Piece, language: Language) -> String {
+    format!(
+        "{}{}",
+        piece_letter(language, piece.piece_type),
+        square_name((piece.x, piece.y))
+    )
+}
+
+/// Spawn and despawn labels as pieces come and go, or the setting changes
+fn sync_labels(
+    mut commands: Commands,
+    assets: Res<UiAssets>,
+    settings: Res<Settings>,
+    board: Res<BoardState>,
+    pieces: Query<Entity, (With<Piece>, Without<Taken>)>,
+    labels: Query<(Entity, &PieceLabel)>,
+) {
+    // The board state is refilled whenever a piece is added, taken or removed
+    if !board.is_changed() && !settings.is_changed() {
+        return;
+    }
+
+    let mut labeled = HashSet::new();
+    for (entity, label) in labels.iter() {
+        if settings.piece_labels && pieces.get(label.0).is_ok() {
+            labeled.insert(label.0);
+        } else {
+            commands.entity(entity).despawn_recursive();
+        }
+    }
+
+    if !settings.piece_labels {
+        return;
+    }
+
+    for piece in pieces.iter().filter(|piece| !labeled.contains(piece)) {
+        commands
+            .spawn_bundle(TextBundle {
+                style: Style {
+                    position_type: PositionType::Absolute,
+                    ..Default::default()
+                },
+                text: assets.text("", 18.0),
+                // Hidden until positioned
+                visible: Visible {
+                    is_visible: false,
+                    is_transparent: true,
+                },
+                ..Default::default()
+            })
+            .insert(PieceLabel(piece));
+    }
+}
+
+/// Keep labels above their piece. Being drawn in screen space, they always face the camera.
+/// Only labels of moved pieces are updated, unless the camera, the window or the settings change.
+fn update_labels(
+    windows: Res<Windows>,
+    settings: Res<Settings>,
+    mut resized_events: EventReader<WindowResized>,
+    camera_query: Query<(&Camera, &GlobalTransform), With<PerspectiveProjection>>,
+    moved_camera: Query<(), MovedCamera>,
+    pieces: Query<(&Piece, &GlobalTransform)>,
+    moved_pieces: Query<(), Or<(Changed<Piece>, Changed<GlobalTransform>)>>,
+    new_labels: Query<(), Added<PieceLabel>>,
+    mut labels: Query<(Entity, &PieceLabel, &mut Style, &mut Text, &mut Visible)>,
+) {
+    let (camera, camera_transform) = match camera_query.iter().next() {
+        Some(camera) => camera,
+        None => return,
+    };
+    let all = resized_events.iter().last().is_some()
+        || moved_camera.iter().next().is_some()
+        || settings.is_changed();
+
+    for (entity, label, mut style, mut text, mut visible) in labels.iter_mut() {
+        if !all && moved_pieces.get(label.0).is_err() && new_labels.get(entity).is_err() {
+            continue;
+        }
+        let (piece, transform) = match pieces.get(label.0) {
+            Ok(piece) => piece,
+            Err(_) => continue,
+        };
+
+        let anchor = transform.translation + Vec3::Y * LABEL_HEIGHT;
+        let position = camera.world_to_screen(&windows, camera_transform, anchor);
+        place_node(
+            &mut style,
+            &mut visible,
+            position.map(|position| position + Vec2::new(LABEL_OFFSET, 0.0)),
+        );
+
+        // Avoid relayouting the text when it is the same
+        let value = label_text(piece, settings.language);
+        if text.sections[0].value != value {
+            text.sections[0].value = value;
+        }
+    }
+}
+
+/// Spawn and despawn move count badges for the pieces of the player to move
+fn sync_move_badges(
+    mut commands: Commands,
+    assets: Res<UiAssets>,
+    settings: Res<Settings>,
+    turn: Res<PlayerTurn>,
+    board: Res<BoardState>,
+    pieces: Query<(Entity, &Piece), Without<Taken>>,
+    badges: Query<(Entity, &MoveCountBadge)>,
+) {
+    // The board state is refilled whenever a piece is added, taken or removed
+    if !board.is_changed() && !turn.is_changed() && !settings.is_changed() {
+        return;
+    }
+
+    let mut badged = HashSet::new();
+    for (entity, badge) in badges.iter() {
+        let shown = settings.move_counts
+            && pieces
+                .get(badge.0)
+                .map_or(false, |(_, piece)| piece.color == turn.0);
+        if shown {
+            badged.insert(badge.0);
+        } else {
+            commands.entity(entity).despawn_recursive();
+        }
+    }
+
+    if !settings.move_counts {
+        return;
+    }
+
+    for (piece, _) in pieces
+        .iter()
+        .filter(|(entity, piece)| piece.color == turn.0 && !badged.contains(entity))
+    {
+        let mut text = assets.text("", 22.0);
+        text.sections[0].style.color = BADGE_COLOR;
+        commands
+            .spawn_bundle(TextBundle {
+                style: Style {
+                    position_type: PositionType::Absolute,
+                    ..Default::default()
+                },
+                text,
+                // Hidden until positioned
+                visible: Visible {
+                    is_visible: false,
+                    is_transparent: true,
+                },
+                ..Default::default()
+            })
+            .insert(MoveCountBadge(piece));
+    }
+}
+
+/// Show how many moves the piece of each badge has, counted again only when the position
+/// or the side to move changes, or badges are added.
+fn count_badge_moves(
+    turn: Res<PlayerTurn>,
+    board: Res<BoardState>,
+    pieces: Query<&Piece, Without<Taken>>,
+    new_badges: Query<(), Added<MoveCountBadge>>,
+    mut badges: Query<(&MoveCountBadge, &mut Text)>,
+) {
+    // The board state changes the frame after every move
+    if !board.is_changed() && !turn.is_changed() && new_badges.iter().next().is_none() {
+        return;
+    }
+
+    for (badge, mut text) in badges.iter_mut() {
+        let piece = match pieces.get(badge.0) {
+            Ok(piece) => piece,
+            Err(_) => continue,
+        };
+        let count = (0..8)
+            .flat_map(|x| (0..8).map(move |y| (x, y)))
+            .filter(|&square| piece.is_move_valid(square, &board.pieces))
+            .count();
+        let value = count.to_string();
+        if text.sections[0].value != value {
+            text.sections[0].value = value;
+        }
+    }
+}
+
+/// Keep badges above their piece, as labels are
+fn update_move_badges(
+    windows: Res<Windows>,
+    mut resized_events: EventReader<WindowResized>,
+    camera_query: Query<(&Camera, &GlobalTransform), With<PerspectiveProjection>>,
+    moved_camera: Query<(), MovedCamera>,
+    pieces: Query<&GlobalTransform, (With<Piece>, Without<Taken>)>,
+    moved_pieces: Query<(), Changed<GlobalTransform>>,
+    new_badges: Query<(), Added<MoveCountBadge>>,
+    mut badges: Query<(Entity, &MoveCountBadge, &mut Style, &mut Visible)>,
+) {
+    let (camera, camera_transform) = match camera_query.iter().next() {
+        Some(camera) => camera,
+        None => return,
+    };
+    let all = resized_events.iter().last().is_some() || moved_camera.iter().next().is_some();
+
+    for (entity, badge, mut style, mut visible) in badges.iter_mut() {
+        if !all && moved_pieces.get(badge.0).is_err() && new_badges.get(entity).is_err() {
+            continue;
+        }
+        let transform = match pieces.get(badge.0) {
+            Ok(transform) => transform,
+            Err(_) => continue,
+        };
+
+        let anchor = transform.translation + Vec3::Y * BADGE_HEIGHT;
+        let position = camera.world_to_screen(&windows, camera_transform, anchor);
+        place_node(&mut style, &mut visible, position);
+    }
+}
+
+/// Show the notation of each move above its destination square
+fn spawn_move_callouts(
+    mut commands: Commands,
+    assets: Res<UiAssets>,
+    mut move_events: EventReader<MoveEvent>,
+) {
+    for event in move_events.iter() {
+        commands
+            .spawn_bundle(TextBundle {
+                style: Style {
+                    position_type: PositionType::Absolute,
+                    ..Default::default()
+                },
+                text: assets.text(&event.san, 36.0),
+                // Hidden until positioned
+                visible: Visible {
+                    is_visible: false,
+                    is_transparent: true,
+                },
+                ..Default::default()
+            })
+            .insert(MoveCallout {
+                anchor: Vec3::new(event.to.0 as f32, 0.0, event.to.1 as f32),
+                timer: Timer::from_seconds(CALLOUT_DURATION, false),
+            });
+    }
+}
+
+/// Raise move callouts above their square, fade them out, and remove them once invisible
+fn update_move_callouts(
+    mut commands: Commands,
+    time: Res<Time>,
+    windows: Res<Windows>,
+    settings: Res<Settings>,
+    camera_query: Query<(&Camera, &GlobalTransform), With<PerspectiveProjection>>,
+    mut callouts: Query<(
+        Entity,
+        &mut MoveCallout,
+        &mut Style,
+        &mut Text,
+        &mut Visible,
+    )>,
+) {
+    let (camera, camera_transform) = match camera_query.iter().next() {
+        Some(camera) => camera,
+        None => return,
+    };
+
+    for (entity, mut callout, mut style, mut text, mut visible) in callouts.iter_mut() {
+        callout.timer.tick(time.delta());
+        if callout.timer.finished() {
+            commands.entity(entity).despawn_recursive();
+            continue;
+        }
+
+        // Callouts stay still with reduced motion
+        let progress = if settings.reduced_motion {
+            0.0
+        } else {
+            callout.timer.percent()
+        };
+        let height = CALLOUT_START_HEIGHT + (CALLOUT_END_HEIGHT - CALLOUT_START_HEIGHT) * progress;
+        let anchor = callout.anchor + Vec3::Y * height;
+        let position = camera.world_to_screen(&windows, camera_transform, anchor);
+        place_node(
+            &mut style,
+            &mut visible,
+            position.map(|position| position + Vec2::new(LABEL_OFFSET, 0.0)),
+        );
+
+        let left = callout.timer.percent_left();
+        let alpha = (left / CALLOUT_FADE).min(1.0);
+        text.sections[0].style.color.set_a(alpha);
+    }
+}
+
+/// Labels Plugin
+///
+/// Shows the name and square of each piece above it, to tell pieces apart
+/// when they hide each other in the perspective view, and optionally how many
+/// moves each piece of the player to move has. The notation of each move
+/// briefly floats above its destination square, for spectators.
+pub struct LabelsPlugin;
+
+impl Plugin for LabelsPlugin {
+    fn build(&self, app: &mut AppBuilder) {
+        app.add_system(sync_labels.system())
+            .add_system(update_labels.system())
+            .add_system(sync_move_badges.system())
+            .add_system(count_badge_moves.system())
+            .add_system(update_move_badges.system())
+            .add_system(spawn_move_callouts.system())
+            .add_system(update_move_callouts.system());
+    }
+}
@@ -0,0 +1,127 @@
+//! Chess game built on Bevy.
+//!
+//! The game is a set of plugins, so it can be embedded in a larger app:
+//!
+//! ```no_run
+//! use bevy::prelude::*;
+//!
+//! App::build()
+//!     .add_plugins(DefaultPlugins)
+//!     .add_plugins(bevy_chess::ChessPlugins)
+//!     .run();
+//! ```
+use bevy::app::PluginGroupBuilder;
+use bevy::prelude::*;
+use bevy_mod_picking::*;
+
+mod arrow;
+
+pub mod assets;
+pub use assets::{AppState, AssetsPlugin};
+
+pub mod board;
+pub use board::{
+    BoardPlugin, HoveredSquare, LoadPositionEvent, MoveEvent, MoveRequestEvent, PlayerTurn,
+    SelectedPiece, SelectedSquare,
+};
+
+pub mod camera;
+pub use camera::CameraPlugin;
+
+#[cfg(feature = "broadcast")]
+pub mod broadcast;
+
+pub mod clock;
+pub use clock::{ChessClock, ClockPlugin, ClockStateEvent, ClockTickEvent};
+
+pub mod compass;
+pub use compass::CompassPlugin;
+
+pub mod cues;
+pub use cues::{AudioCues, CuesPlugin};
+
+pub mod config;
+pub use config::{ColorChoice, GameConfig, PlayerColor};
+
+pub mod error;
+pub use error::ChessError;
+
+pub mod fen;
+
+pub mod help;
+pub use help::HelpPlugin;
+
+pub mod highlight;
+pub use highlight::{HighlightLayer, HighlightPlugin, Highlights};
+
+pub mod history;
+pub use history::{AnnotateMoveEvent, HistoryPlugin, MoveHistory};
+
+pub mod kit;
+pub use kit::KitConfig;
+
+pub mod labels;
+pub use labels::LabelsPlugin;
+
+pub mod locale;
+pub use locale::LocalePlugin;
+
+pub mod matches;
+pub use matches::{MatchPlugin, MatchResult, MatchScore};
+
+#[cfg(feature = "notify")]
+pub mod notify;
+
+pub mod paste;
+pub use paste::PastePlugin;
+
+pub mod pieces;
+pub use pieces::{BoardState, PiecePlugin};
+
+pub mod practice;
+pub use practice::PracticePlugin;
+
+pub mod settings;
+pub use settings::{KeyBindings, KeyProfile, Settings, SettingsPlugin};
+
+pub mod status;
+pub use status::{ErrorLog, StatusMessage, StatusPlugin};
+
+pub mod ui;
+pub use ui::UiPlugin;
+
+/// All the plugins of the game, including mesh picking.
+pub struct ChessPlugins;
+
+impl PluginGroup for ChessPlugins {
+    fn build(&mut self, group: &mut PluginGroupBuilder) {
+        group
+            .add(PickingPlugin)
+            .add(InteractablePickingPlugin)
+            .add(HighlightablePickingPlugin)
+            .add(AssetsPlugin)
+            .add(BoardPlugin)
+            .add(PiecePlugin)
+            .add(HighlightPlugin)
+            .add(ClockPlugin)
+            .add(HistoryPlugin)
+            .add(UiPlugin)
+            .add(SettingsPlugin)
+            .add(PracticePlugin)
+            .add(MatchPlugin)
+            .add(LabelsPlugin)
+            .add(CameraPlugin)
+            .add(StatusPlugin)
+            .add(LocalePlugin)
+            .add(PastePlugin)
+            .add(CompassPlugin)
+            .add(CuesPlugin)
+            .add(HelpPlugin);
+
+        #[cfg(feature = "broadcast")]
+        group.add(broadcast::BroadcastPlugin);
+
+        #[cfg(feature = "notify")]
+        group.add(notify::NotifyPlugin);
+    }
+}
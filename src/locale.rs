@@ -0,0 +1,378 @@
+use bevy::prelude::*;
+
+use crate::fen::FenError;
+use crate::pieces::{PieceColor, PieceType};
+use crate::settings::Settings;
+
+/// Language of the UI.
+#[derive(Clone, Copy, PartialEq)]
+pub enum Language {
+    English,
+    French,
+}
+
+impl Default for Language {
+    fn default() -> Self {
+        Language::English
+    }
+}
+
+impl Language {
+    /// All languages, in picker order.
+    pub const ALL: &'static [Language] = &[Language::English, Language::French];
+
+    /// Name of the language, in that language.
+    pub fn name(self) -> &'static str {
+        match self {
+            Language::English => "English",
+            Language::French => "Français",
+        }
+    }
+
+    /// Language following this one in the picker.
+    pub fn next(self) -> Language {
+        let i = Language::ALL.iter().position(|&l| l == self).unwrap_or(0);
+        Language::ALL[(i + 1) % Language::ALL.len()]
+    }
+
+    fn strings(self) -> &'static [(&'static str, &'static str)] {
+        match self {
+            Language::English => ENGLISH,
+            Language::French => FRENCH,
+        }
+    }
+}
+
+const ENGLISH: &[(&str, &str)] = &[
+    ("white", "White"),
+    ("black", "Black"),
+    ("next_move", "Next move: {}"),
+    ("to_move", "{} to move"),
+    ("out_of_time", "{} ran out of time"),
+    ("illegal_move", "Illegal move"),
+    ("auto_move", "{} took too long, a random move was played"),
+    ("wins", "{} wins"),
+    ("match_score", "Match: {0} (best of {1})"),
+    ("match_won", "Match won {}"),
+    ("match_lost", "Match lost {}"),
+    ("match_tied", "Match tied {}"),
+    ("next_game", "Next game"),
+    ("cannot_load", "Cannot load {}"),
+    ("unexpected_error", "Something went wrong: {}"),
+    (
+        "fen_bad_placement",
+        "Invalid FEN: the board needs 8 ranks of 8 squares",
+    ),
+    ("fen_bad_piece", "Invalid FEN: unknown piece {}"),
+    (
+        "fen_bad_side_to_move",
+        "Invalid FEN: the side to move must be w or b",
+    ),
+    (
+        "fen_bad_move_number",
+        "Invalid FEN: the move number must be positive",
+    ),
+    ("loading", "Loading..."),
+    (
+        "loading_failed",
+        "Cannot load the game assets, see the error log",
+    ),
+    ("moves", "Moves"),
+    ("copy_moves", "Copy moves"),
+    ("copy_fen", "Copy FEN"),
+    ("copied", "Copied to clipboard"),
+    ("copy_failed", "Cannot access the clipboard"),
+    ("pgn_unsupported", "Only FEN positions can be pasted"),
+    ("discard_game", "Discard the current game?"),
+    ("yes", "Yes"),
+    ("no", "No"),
+    ("confirm_move", "Confirm move"),
+    ("practice", "Practice"),
+    ("solved", "Solved!"),
+    ("failed", "Failed"),
+    ("moves_held", "{0}/{1} moves held"),
+    ("preset_kq_k", "K+Q vs K"),
+    ("goal_kq_k", "White to move: mate with king and queen"),
+    ("preset_kr_k", "K+R vs K"),
+    ("goal_kr_k", "White to move: mate with king and rook"),
+    ("preset_lucena", "Lucena"),
+    ("goal_lucena", "White to move: build a bridge and win"),
+    ("preset_philidor", "Philidor"),
+    (
+        "goal_philidor",
+        "Black to move: hold the third rank for {} moves",
+    ),
+    ("preset_r_b", "R vs B"),
+    ("goal_r_b", "White to move: Black holds for {} moves"),
+    ("settings", "Settings"),
+    ("confirm_moves", "Confirm moves"),
+    ("piece_labels", "Piece labels"),
+    ("camera_cuts", "Camera cuts"),
+    ("auto_camera", "Auto camera"),
+    ("reduced_motion", "Reduce motion"),
+    ("move_counts", "Move counts"),
+    ("show_threats", "Show threats"),
+    ("focus_mode", "Focus mode"),
+    ("audio_cues", "Audio cues"),
+    ("silent_clock", "Silent clock"),
+    ("animation_speed", "Animation speed"),
+    ("slow", "Slow"),
+    ("normal", "Normal"),
+    ("fast", "Fast"),
+    ("instant", "Instant"),
+    ("piece_size", "Piece size"),
+    ("small", "Small"),
+    ("large", "Large"),
+    ("board_gap", "Gap between squares"),
+    ("no_gap", "None"),
+    ("thin", "Thin"),
+    ("wide", "Wide"),
+    ("notation", "Notation"),
+    ("san", "Short"),
+    ("long", "Long"),
+    ("figurine", "Figurine"),
+    ("clock_style", "Clock"),
+    ("digital", "Digital"),
+    ("analog", "Analog"),
+    ("clock_placement", "Clock position"),
+    ("corner", "Corner"),
+    ("beside_board", "Beside the board"),
+    ("key_profile", "Keys"),
+    ("standard", "Standard"),
+    ("left_hand", "Left hand"),
+    ("right_hand", "Right hand"),
+    ("language", "Language"),
+    ("help_controls", "Controls"),
+    ("help_rules", "Rules"),
+    ("help_click", "select a piece, then its destination"),
+    ("help_type_square", "select a square by typing it"),
+    ("help_cursor", "move the square cursor"),
+    ("help_select", "select the square under the cursor"),
+    ("help_cancel", "drop the typed file, close this help"),
+    ("help_confirm", "confirm the pending move"),
+    ("help_menu", "open or close the settings"),
+    ("help_paste", "paste a FEN position"),
+    ("help_help", "show or hide this help"),
+    ("help_time_control", "Time control: {}"),
+    ("help_move_limit", "Move time limit: {}"),
+    ("on", "On"),
+    ("off", "Off"),
+];
+
+const FRENCH: &[(&str, &str)] = &[
+    ("white", "Blancs"),
+    ("black", "Noirs"),
+    ("next_move", "Trait : {}"),
+    ("to_move", "Aux {} de jouer"),
+    ("out_of_time", "Temps écoulé pour les {}"),
+    ("illegal_move", "Coup illégal"),
+    (
+        "auto_move",
+        "Trop lent : un coup au hasard a été joué pour les {}",
+    ),
+    ("wins", "Victoire des {}"),
+    ("match_score", "Match : {0} (au meilleur des {1})"),
+    ("match_won", "Match gagné {}"),
+    ("match_lost", "Match perdu {}"),
+    ("match_tied", "Match nul {}"),
+    ("next_game", "Partie suivante"),
+    ("cannot_load", "Impossible de charger {}"),
+    ("unexpected_error", "Erreur inattendue : {}"),
+    (
+        "fen_bad_placement",
+        "FEN invalide : il faut 8 rangées de 8 cases",
+    ),
+    ("fen_bad_piece", "FEN invalide : pièce inconnue {}"),
+    (
+        "fen_bad_side_to_move",
+        "FEN invalide : le trait doit être w ou b",
+    ),
+    (
+        "fen_bad_move_number",
+        "FEN invalide : le numéro de coup doit être positif",
+    ),
+    ("loading", "Chargement..."),
+    (
+        "loading_failed",
+        "Impossible de charger les ressources du jeu, voir le journal des erreurs",
+    ),
+    ("moves", "Coups"),
+    ("copy_moves", "Copier les coups"),
+    ("copy_fen", "Copier la FEN"),
+    ("copied", "Copié dans le presse-papiers"),
+    ("copy_failed", "Presse-papiers inaccessible"),
+    (
+        "pgn_unsupported",
+        "Seules les positions FEN peuvent être collées",
+    ),
+    ("discard_game", "Abandonner la partie en cours ?"),
+    ("yes", "Oui"),
+    ("no", "Non"),
+    ("confirm_move", "Confirmer le coup"),
+    ("practice", "Entraînement"),
+    ("solved", "Réussi !"),
+    ("failed", "Échoué"),
+    ("moves_held", "{0}/{1} coups tenus"),
+    ("preset_kq_k", "R+D contre R"),
+    (
+        "goal_kq_k",
+        "Blancs au trait : mater avec le roi et la dame",
+    ),
+    ("preset_kr_k", "R+T contre R"),
+    (
+        "goal_kr_k",
+        "Blancs au trait : mater avec le roi et la tour",
+    ),
+    ("preset_lucena", "Lucena"),
+    (
+        "goal_lucena",
+        "Blancs au trait : construire le pont et gagner",
+    ),
+    ("preset_philidor", "Philidor"),
+    (
+        "goal_philidor",
+        "Noirs au trait : tenir la troisième rangée pendant {} coups",
+    ),
+    ("preset_r_b", "T contre F"),
+    ("goal_r_b", "Blancs au trait : les Noirs tiennent {} coups"),
+    ("settings", "Réglages"),
+    ("confirm_moves", "Confirmer les coups"),
+    ("piece_labels", "Noms des pièces"),
+    ("camera_cuts", "Effets de caméra"),
+    ("auto_camera", "Caméra automatique"),
+    ("reduced_motion", "Réduire les animations"),
+    ("move_counts", "Nombre de coups"),
+    ("show_threats", "Pièces en prise"),
+    ("focus_mode", "Mode concentration"),
+    ("audio_cues", "Signaux sonores"),
+    ("silent_clock", "Pendule silencieuse"),
+    ("animation_speed", "Vitesse des animations"),
+    ("slow", "Lente"),
+    ("normal", "Normale"),
+    ("fast", "Rapide"),
+    ("instant", "Instantanée"),
+    ("piece_size", "Taille des pièces"),
+    ("small", "Petite"),
+    ("large", "Grande"),
+    ("board_gap", "Espace entre les cases"),
+    ("no_gap", "Aucun"),
+    ("thin", "Fin"),
+    ("wide", "Large"),
+    ("notation", "Notation"),
+    ("san", "Abrégée"),
+    ("long", "Complète"),
+    ("figurine", "Figurine"),
+    ("clock_style", "Pendule"),
+    ("digital", "Numérique"),
+    ("analog", "Analogique"),
+    ("clock_placement", "Position des pendules"),
+    ("corner", "Coin"),
+    ("beside_board", "À côté de l'échiquier"),
+    ("key_profile", "Touches"),
+    ("standard", "Standard"),
+    ("left_hand", "Main gauche"),
+    ("right_hand", "Main droite"),
+    ("language", "Langue"),
+    ("help_controls", "Commandes"),
+    ("help_rules", "Règles"),
+    ("help_click", "choisir une pièce, puis sa destination"),
+    ("help_type_square", "choisir une case en la tapant"),
+    ("help_cursor", "déplacer le curseur de case"),
+    ("help_select", "choisir la case sous le curseur"),
+    ("help_cancel", "effacer la colonne tapée, fermer l'aide"),
+    ("help_confirm", "confirmer le coup en attente"),
+    ("help_menu", "ouvrir ou fermer les réglages"),
+    ("help_paste", "coller une position FEN"),
+    ("help_help", "afficher ou masquer l'aide"),
+    ("help_time_control", "Cadence : {}"),
+    ("help_move_limit", "Temps maximum par coup : {}"),
+    ("on", "Oui"),
+    ("off", "Non"),
+];
+
+/// Translate a UI string, falling back to English, then to the key itself.
+pub fn tr(language: Language, key: &'static str) -> &'static str {
+    let find = |language: Language| {
+        language
+            .strings()
+            .iter()
+            .find(|(k, _)| *k == key)
+            .map(|(_, value)| *value)
+    };
+    find(language)
+        .or_else(|| find(Language::English))
+        .unwrap_or(key)
+}
+
+/// Translate a UI string with a single `{}` placeholder.
+pub fn tr_with(language: Language, key: &'static str, value: &str) -> String {
+    tr(language, key).replacen("{}", value, 1)
+}
+
+/// Translate a UI string with numbered `{0}`, `{1}`... placeholders, which
+/// translations may reorder.
+pub fn tr_args(language: Language, key: &'static str, values: &[&str]) -> String {
+    let mut text = tr(language, key).to_string();
+    for (i, value) in values.iter().enumerate() {
+        text = text.replace(&format!("{{{}}}", i), value);
+    }
+    text
+}
+
+/// Name of a side, e.g. "White".
+pub fn color_name(language: Language, color: PieceColor) -> &'static str {
+    match color {
+        PieceColor::White => tr(language, "white"),
+        PieceColor::Black => tr(language, "black"),
+    }
+}
+
+/// Reason a FEN string cannot be loaded, for the status bar.
+pub fn fen_error(language: Language, error: &FenError) -> String {
+    match error {
+        FenError::BadPlacement => tr(language, "fen_bad_placement").to_string(),
+        FenError::BadPiece(c) => tr_with(language, "fen_bad_piece", &c.to_string()),
+        FenError::BadSideToMove => tr(language, "fen_bad_side_to_move").to_string(),
+        FenError::BadMoveNumber => tr(language, "fen_bad_move_number").to_string(),
+    }
+}
+
+/// Letter of a piece in algebraic notation, empty for pawns.
+pub fn piece_letter(language: Language, piece_type: PieceType) -> &'static str {
+    match language {
+        Language::English => piece_type.notation(),
+        Language::French => match piece_type {
+            PieceType::King => "R",
+            PieceType::Queen => "D",
+            PieceType::Bishop => "F",
+            PieceType::Knight => "C",
+            PieceType::Rook => "T",
+            PieceType::Pawn => "",
+        },
+    }
+}
+
+/// Text showing a fixed UI string, translated again when the language changes.
+pub struct Localized(pub &'static str);
+
+/// Translate fixed texts when the language changes
+fn update_localized_texts(settings: Res<Settings>, mut query: Query<(&mut Text, &Localized)>) {
+    if !settings.is_changed() {
+        return;
+    }
+    for (mut text, localized) in query.iter_mut() {
+        let value = tr(settings.language, localized.0);
+        if text.sections[0].value != value {
+            text.sections[0].value = value.to_string();
+        }
+    }
+}
+
+/// Locale Plugin
+pub struct LocalePlugin;
+
+impl Plugin for LocalePlugin {
+    fn build(&self, app: &mut AppBuilder) {
+        app.add_system(update_localized_texts.system());
+    }
+}
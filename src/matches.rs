@@ -0,0 +1,292 @@
+use bevy::prelude::*;
+
+use crate::board::{GameOverEvent, LoadPositionEvent};
+use crate::config::{GameConfig, PlayerColor};
+use crate::locale::*;
+use crate::settings::Settings;
+use crate::ui::{StatusBar, UiAssets};
+
+/// Outcome of a finished match, for the local player.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum MatchResult {
+    Won,
+    Lost,
+    Tied,
+}
+
+/// Score of a best-of-N match, for the local player against the opponent.
+#[derive(Default)]
+pub struct MatchScore {
+    /// Number of games of the match, `None` outside match play
+    pub games: Option<u32>,
+    /// Games finished so far
+    pub played: u32,
+    /// Games won by the local player
+    pub player: u32,
+    /// Games won by the opponent
+    pub opponent: u32,
+}
+
+impl MatchScore {
+    /// Record a finished game.
+    pub fn record(&mut self, player_won: bool) {
+        self.played += 1;
+        if player_won {
+            self.player += 1;
+        } else {
+            self.opponent += 1;
+        }
+    }
+
+    /// Result of the match, once all games are played or one side cannot be caught.
+    pub fn result(&self) -> Option<MatchResult> {
+        let games = self.games?;
+        let remaining = games.saturating_sub(self.played);
+        if self.player > self.opponent + remaining {
+            Some(MatchResult::Won)
+        } else if self.opponent > self.player + remaining {
+            Some(MatchResult::Lost)
+        } else if remaining == 0 {
+            Some(MatchResult::Tied)
+        } else {
+            None
+        }
+    }
+}
+
+impl FromWorld for MatchScore {
+    fn from_world(world: &mut World) -> Self {
+        let config = world.get_resource_or_insert_with(GameConfig::default);
+        Self {
+            games: config.match_games,
+            ..Default::default()
+        }
+    }
+}
+
+/// Text showing the match score
+struct MatchScoreText;
+
+/// Node holding the next game button
+struct NextGameBar;
+
+/// Button starting the next game of the match, with colors swapped
+struct NextGameButton;
+
+/// Show the match score in the status bar
+fn init_match_ui(
+    mut commands: Commands,
+    assets: Res<UiAssets>,
+    settings: Res<Settings>,
+    score: Res<MatchScore>,
+    status_bar_query: Query<Entity, Added<StatusBar>>,
+) {
+    if score.games.is_none() {
+        return;
+    }
+
+    for status_bar in status_bar_query.iter() {
+        commands.entity(status_bar).with_children(|parent| {
+            parent
+                .spawn_bundle(TextBundle {
+                    style: Style {
+                        margin: Rect {
+                            left: Val::Px(20.0),
+                            ..Default::default()
+                        },
+                        ..Default::default()
+                    },
+                    text: assets.text(&score_text(&score, settings.language), 24.0),
+                    ..Default::default()
+                })
+                .insert(MatchScoreText);
+        });
+    }
+}
+
+/// Score as shown to the players, e.g. "Match: 2 - 1 (best of 5)".
+fn score_text(score: &MatchScore, language: Language) -> String {
+    let result = format!("{} - {}", score.player, score.opponent);
+    match score.result() {
+        Some(MatchResult::Won) => tr_with(language, "match_won", &result),
+        Some(MatchResult::Lost) => tr_with(language, "match_lost", &result),
+        Some(MatchResult::Tied) => tr_with(language, "match_tied", &result),
+        None => {
+            let games = score.games.unwrap_or(0).to_string();
+            tr_args(language, "match_score", &[&result, &games])
+        }
+    }
+}
+
+/// Count the winner of each game, and offer the next game until the match is over
+fn score_games(
+    mut commands: Commands,
+    assets: Res<UiAssets>,
+    settings: Res<Settings>,
+    player: Res<PlayerColor>,
+    mut score: ResMut<MatchScore>,
+    mut game_over_events: EventReader<GameOverEvent>,
+    bar_query: Query<Entity, With<NextGameBar>>,
+    status_bar_query: Query<Entity, With<StatusBar>>,
+) {
+    let event = match game_over_events.iter().last() {
+        Some(event) => event,
+        None => return,
+    };
+    if score.games.is_none() || score.result().is_some() {
+        return;
+    }
+
+    score.record(event.winner == player.0);
+    if score.result().is_some() || bar_query.iter().next().is_some() {
+        return;
+    }
+
+    for status_bar in status_bar_query.iter() {
+        commands.entity(status_bar).with_children(|parent| {
+            parent
+                .spawn_bundle(NodeBundle {
+                    style: Style {
+                        margin: Rect {
+                            left: Val::Px(20.0),
+                            ..Default::default()
+                        },
+                        ..Default::default()
+                    },
+                    material: assets.transparent.clone(),
+                    ..Default::default()
+                })
+                .insert(NextGameBar)
+                .with_children(|parent| {
+                    assets.spawn_localized_button(
+                        parent,
+                        settings.language,
+                        "next_game",
+                        30.0,
+                        NextGameButton,
+                    );
+                });
+        });
+    }
+}
+
+/// Start the next game from the configured position, the players swapping colors
+fn click_next_game(
+    config: Res<GameConfig>,
+    mut player: ResMut<PlayerColor>,
+    mut load_position_events: EventWriter<LoadPositionEvent>,
+    interaction_query: Query<&Interaction, (Changed<Interaction>, With<NextGameButton>)>,
+) {
+    if !interaction_query
+        .iter()
+        .any(|interaction| *interaction == Interaction::Clicked)
+    {
+        return;
+    }
+
+    player.0 = player.0.opponent();
+    load_position_events.send(LoadPositionEvent {
+        position: config.starting_position(),
+    });
+}
+
+/// Remove the next game button once a game starts, from it or from the menus
+fn hide_next_game(
+    mut commands: Commands,
+    mut load_position_events: EventReader<LoadPositionEvent>,
+    bar_query: Query<Entity, With<NextGameBar>>,
+) {
+    if load_position_events.iter().last().is_none() {
+        return;
+    }
+    for entity in bar_query.iter() {
+        commands.entity(entity).despawn_recursive();
+    }
+}
+
+/// Keep the score text up to date
+fn match_score_update(
+    settings: Res<Settings>,
+    score: Res<MatchScore>,
+    mut query: Query<&mut Text, With<MatchScoreText>>,
+) {
+    if !score.is_changed() && !settings.is_changed() {
+        return;
+    }
+    for mut text in query.iter_mut() {
+        text.sections[0].value = score_text(&score, settings.language);
+    }
+}
+
+/// Match Plugin
+///
+/// Plays a best-of-N match when `GameConfig::match_games` is set, e.g. with
+/// `--match 5`: the score is shown in the status bar, and a button starts the
+/// next game with the players swapping colors, until one side cannot be caught
+/// or all games are played.
+pub struct MatchPlugin;
+
+impl Plugin for MatchPlugin {
+    fn build(&self, app: &mut AppBuilder) {
+        app.init_resource::<MatchScore>()
+            .add_system(init_match_ui.system())
+            .add_system(score_games.system())
+            .add_system(click_next_game.system())
+            .add_system(hide_next_game.system())
+            .add_system(match_score_update.system());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn play(games: u32, player_wins: &[bool]) -> MatchScore {
+        let mut score = MatchScore {
+            games: Some(games),
+            ..Default::default()
+        };
+        for &won in player_wins {
+            score.record(won);
+        }
+        score
+    }
+
+    #[test]
+    fn odd_match_ends_when_one_side_cannot_be_caught() {
+        assert_eq!(play(5, &[true, true]).result(), None);
+        assert_eq!(
+            play(5, &[true, true, true]).result(),
+            Some(MatchResult::Won)
+        );
+        assert_eq!(
+            play(5, &[true, false, false, false]).result(),
+            Some(MatchResult::Lost)
+        );
+    }
+
+    #[test]
+    fn even_match_can_be_tied() {
+        assert_eq!(play(4, &[true, false, true]).result(), None);
+        assert_eq!(
+            play(4, &[true, false, true, false]).result(),
+            Some(MatchResult::Tied)
+        );
+        assert_eq!(
+            play(4, &[true, true, true]).result(),
+            Some(MatchResult::Won)
+        );
+    }
+
+    #[test]
+    fn match_ends_after_all_games() {
+        let score = play(2, &[false, true]);
+        assert_eq!(score.played, 2);
+        assert_eq!(score.result(), Some(MatchResult::Tied));
+    }
+
+    #[test]
+    fn no_result_outside_match_play() {
+        assert_eq!(MatchScore::default().result(), None);
+    }
+}
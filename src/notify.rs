@@ -0,0 +1,71 @@
+use bevy::prelude::*;
+
+use crate::board::PlayerTurn;
+use crate::fen::to_fen;
+use crate::history::MoveHistory;
+use crate::pieces::*;
+
+use std::fs;
+use std::io::{self, Write};
+use std::path::PathBuf;
+
+/// Where to report the position, insert it before the plugins to change it.
+#[derive(Default)]
+pub struct NotifyConfig {
+    /// File rewritten with the FEN after every move, or `None` to print JSON lines on stdout
+    pub path: Option<PathBuf>,
+}
+
+/// Report the position whenever it changes
+fn notify_position(
+    config: Res<NotifyConfig>,
+    turn: Res<PlayerTurn>,
+    history: Res<MoveHistory>,
+    board: Res<BoardState>,
+    mut last_fen: Local<String>,
+) {
+    // The board state changes the frame after every move
+    if !board.is_changed() {
+        return;
+    }
+
+    let fen = to_fen(&board.pieces, turn.0, history.fullmove());
+    if fen == *last_fen {
+        return;
+    }
+
+    let result = match &config.path {
+        Some(path) => fs::write(path, format!("{}\n", fen)),
+        None => {
+            let last_move = match history.moves.last() {
+                Some(record) => format!("\"{}\"", record.san),
+                None => String::from("null"),
+            };
+            let line = format!(
+                "{{\"fen\":\"{}\",\"ply\":{},\"last_move\":{}}}",
+                fen,
+                history.moves.len(),
+                last_move,
+            );
+            let mut stdout = io::stdout();
+            writeln!(stdout, "{}", line).and_then(|_| stdout.flush())
+        }
+    };
+    if let Err(err) = result {
+        warn!("cannot report the position: {}", err);
+    }
+    *last_fen = fen;
+}
+
+/// Notify Plugin
+///
+/// Reports the FEN after every move, for external scripts, broadcast tools or
+/// electronic board bridges following the game.
+pub struct NotifyPlugin;
+
+impl Plugin for NotifyPlugin {
+    fn build(&self, app: &mut AppBuilder) {
+        app.init_resource::<NotifyConfig>()
+            .add_system(notify_position.system());
+    }
+}
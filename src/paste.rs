@@ -0,0 +1,171 @@
+use bevy::prelude::*;
+
+use crate::board::LoadPositionEvent;
+use crate::fen::{parse_fen, Position};
+use crate::history::MoveHistory;
+use crate::locale::*;
+use crate::settings::{KeyBindings, Settings};
+use crate::status::StatusMessage;
+use crate::ui::UiAssets;
+
+/// Longest text taken as a FEN, a full position with counters is under 100 bytes.
+const MAX_PASTE_LEN: usize = 128;
+
+/// Position pasted over a game in progress, waiting for confirmation.
+#[derive(Default)]
+struct PendingPaste(Option<Position>);
+
+/// Root of the dialog confirming a paste
+struct PasteDialog;
+
+/// Button answering the paste dialog, true to discard the current game
+struct PasteDialogButton(bool);
+
+/// Load a FEN from the clipboard on Ctrl+V
+fn paste_position(
+    mut commands: Commands,
+    keys: Res<Input<KeyCode>>,
+    bindings: Res<KeyBindings>,
+    assets: Res<UiAssets>,
+    settings: Res<Settings>,
+    history: Res<MoveHistory>,
+    mut pending_paste: ResMut<PendingPaste>,
+    mut load_position_events: EventWriter<LoadPositionEvent>,
+    mut status_messages: EventWriter<StatusMessage>,
+) {
+    let ctrl = keys.pressed(KeyCode::LControl)
+        || keys.pressed(KeyCode::RControl)
+        || keys.pressed(KeyCode::LWin)
+        || keys.pressed(KeyCode::RWin);
+    if !ctrl || !keys.just_pressed(bindings.paste) || pending_paste.0.is_some() {
+        return;
+    }
+
+    let text = match arboard::Clipboard::new().and_then(|mut clipboard| clipboard.get_text()) {
+        Ok(text) => text,
+        Err(error) => {
+            warn!("Cannot read the clipboard: {}", error);
+            status_messages.send(StatusMessage::new(tr(settings.language, "copy_failed")));
+            return;
+        }
+    };
+    let text = text.trim();
+
+    // The clipboard can hold anything, only a single short line can be a FEN.
+    // PGN starts with tag pairs or a move number.
+    if text.len() > MAX_PASTE_LEN
+        || text.contains('\n')
+        || text.starts_with('[')
+        || text.starts_with("1.")
+    {
+        status_messages.send(StatusMessage::new(tr(settings.language, "pgn_unsupported")));
+        return;
+    }
+    let position = match parse_fen(text) {
+        Ok(position) => position,
+        Err(error) => {
+            warn!("Cannot load pasted FEN {:?}: {}", text, error);
+            status_messages.send(StatusMessage(fen_error(settings.language, &error)));
+            return;
+        }
+    };
+
+    if history.moves.is_empty() {
+        load_position_events.send(LoadPositionEvent { position });
+        return;
+    }
+
+    // Ask before discarding the game in progress
+    pending_paste.0 = Some(position);
+    commands
+        .spawn_bundle(NodeBundle {
+            style: Style {
+                size: Size::new(Val::Percent(100.0), Val::Percent(100.0)),
+                position_type: PositionType::Absolute,
+                justify_content: JustifyContent::Center,
+                align_items: AlignItems::Center,
+                ..Default::default()
+            },
+            material: assets.transparent.clone(),
+            ..Default::default()
+        })
+        .insert(PasteDialog)
+        .with_children(|parent| {
+            parent
+                .spawn_bundle(NodeBundle {
+                    style: Style {
+                        // Reversed as UI nodes are laid out from the bottom
+                        flex_direction: FlexDirection::ColumnReverse,
+                        align_items: AlignItems::Center,
+                        padding: Rect::all(Val::Px(12.0)),
+                        ..Default::default()
+                    },
+                    material: assets.panel.clone(),
+                    ..Default::default()
+                })
+                .with_children(|parent| {
+                    parent
+                        .spawn_bundle(TextBundle {
+                            text: assets.text(tr(settings.language, "discard_game"), 30.0),
+                            ..Default::default()
+                        })
+                        .insert(Localized("discard_game"));
+                    parent
+                        .spawn_bundle(NodeBundle {
+                            material: assets.transparent.clone(),
+                            ..Default::default()
+                        })
+                        .with_children(|parent| {
+                            assets.spawn_localized_button(
+                                parent,
+                                settings.language,
+                                "yes",
+                                24.0,
+                                PasteDialogButton(true),
+                            );
+                            assets.spawn_localized_button(
+                                parent,
+                                settings.language,
+                                "no",
+                                24.0,
+                                PasteDialogButton(false),
+                            );
+                        });
+                });
+        });
+}
+
+/// Load or drop the pasted position once the dialog is answered
+fn answer_paste_dialog(
+    mut commands: Commands,
+    mut pending_paste: ResMut<PendingPaste>,
+    mut load_position_events: EventWriter<LoadPositionEvent>,
+    button_query: Query<(&Interaction, &PasteDialogButton), Changed<Interaction>>,
+    dialog_query: Query<Entity, With<PasteDialog>>,
+) {
+    for (interaction, button) in button_query.iter() {
+        if *interaction != Interaction::Clicked {
+            continue;
+        }
+
+        if let Some(position) = pending_paste.0.take() {
+            if button.0 {
+                load_position_events.send(LoadPositionEvent { position });
+            }
+        }
+        for dialog in dialog_query.iter() {
+            commands.entity(dialog).despawn_recursive();
+        }
+    }
+}
+
+/// Paste Plugin
+pub struct PastePlugin;
+
+impl Plugin for PastePlugin {
+    fn build(&self, app: &mut AppBuilder) {
+        app.init_resource::<PendingPaste>()
+            .add_system(paste_position.system())
+            .add_system(answer_paste_dialog.system());
+    }
+}
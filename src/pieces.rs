@@ -1,45 +1,58 @@
 use bevy::prelude::*;
+use bevy_mod_picking::PickableBundle;
 
-/// Helper macro for spawning a chess piece.
-#[macro_export]
-macro_rules! spawn_piece {
-    ($commands:expr, ($x:expr, $y:expr), $translation:expr, $type:expr, $color:expr, $material:expr, $($mesh:expr),+$(,)?) => {{
-        use bevy::prelude::{PbrBundle, Transform, Vec3};
-        $commands
-            .spawn_bundle(PbrBundle {
-                transform: Transform::from_translation(Vec3::new($x as f32, 0.0, $y as f32)),
-                ..Default::default()
-            })
-            .insert(Piece {
-                color: $color,
-                piece_type: $type,
-                x: $x,
-                y: $y,
-            })
-            .with_children(|parent| {
-                $(
-                    parent.spawn_bundle(PbrBundle {
-                        mesh: $mesh,
-                        material: $material,
-                        transform: {
-                            let mut transform = Transform::from_translation($translation);
-                            transform.apply_non_uniform_scale(Vec3::new(0.2, 0.2, 0.2));
-                            transform
-                        },
-                        ..Default::default()
-                    });
-                )+
-            });
-    }};
-}
+use crate::assets::*;
+use crate::board::{LoadPositionEvent, MoveEvent, PlayerTurn, Taken};
+use crate::config::GameConfig;
+use crate::error::{report_error, ChessError};
+use crate::settings::Settings;
+
+use std::collections::HashMap;
+use std::fmt;
+use std::time::Instant;
+
+/// Distance to their destination under which pieces stop moving.
+const ARRIVAL_DISTANCE: f32 = 0.1;
+/// Period of the breathing of the pieces to move, in seconds.
+const BREATH_PERIOD: f32 = 2.5;
+/// Scale change of the pieces to move at the top of a breath.
+const BREATH_SCALE: f32 = 0.03;
 
 /// Piece Plugin
 pub struct PiecePlugin;
 
 impl Plugin for PiecePlugin {
     fn build(&self, app: &mut AppBuilder) {
-        app.add_startup_system(create_pieces.system())
-            .add_system(move_pieces.system());
+        app.init_resource::<PieceIndex>()
+            .init_resource::<BoardState>()
+            .add_system_set(
+                SystemSet::on_enter(AppState::Playing).with_system(create_pieces.system()),
+            )
+            .add_system(load_position.system())
+            .add_system(move_pieces.system())
+            .add_system(move_captured_pieces.system())
+            .add_system(shake_pieces.system())
+            .add_system(breathe_pieces.system())
+            .add_system(topple_pieces.system())
+            .add_system(resize_pieces.system())
+            .add_system(reload_models.system().after("load_kit"))
+            .add_system(
+                start_animation
+                    .system()
+                    .chain(report_error.system())
+                    .after("play_move"),
+            )
+            .add_system_set(
+                SystemSet::on_update(AppState::Animating)
+                    .with_system(end_animation.system().chain(report_error.system())),
+            )
+            // Removed pieces are only visible once commands have been applied
+            .add_system_to_stage(CoreStage::PostUpdate, index_pieces.system())
+            // Taken pieces are only marked once commands have been applied
+            .add_system_to_stage(
+                CoreStage::PostUpdate,
+                update_board_state.system().label("board_state"),
+            );
     }
 }
 
@@ -50,8 +63,27 @@ pub enum PieceColor {
     Black,
 }
 
+impl PieceColor {
+    /// Color of the other player.
+    pub fn opponent(self) -> PieceColor {
+        match self {
+            PieceColor::White => PieceColor::Black,
+            PieceColor::Black => PieceColor::White,
+        }
+    }
+}
+
+impl fmt::Display for PieceColor {
+    fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            PieceColor::White => write!(formatter, "White"),
+            PieceColor::Black => write!(formatter, "Black"),
+        }
+    }
+}
+
 /// Type of a chess piece
-#[derive(Clone, Copy, PartialEq)]
+#[derive(Clone, Copy, PartialEq, Debug)]
 pub enum PieceType {
     King,
     Queen,
@@ -61,6 +93,32 @@ pub enum PieceType {
     Pawn,
 }
 
+impl PieceType {
+    /// Letter of the piece in algebraic notation, empty for pawns.
+    pub fn notation(self) -> &'static str {
+        match self {
+            PieceType::King => "K",
+            PieceType::Queen => "Q",
+            PieceType::Bishop => "B",
+            PieceType::Knight => "N",
+            PieceType::Rook => "R",
+            PieceType::Pawn => "",
+        }
+    }
+
+    /// Glyph of the piece in figurine notation, empty for pawns.
+    pub fn figurine(self) -> &'static str {
+        match self {
+            PieceType::King => "♔",
+            PieceType::Queen => "♕",
+            PieceType::Bishop => "♗",
+            PieceType::Knight => "♘",
+            PieceType::Rook => "♖",
+            PieceType::Pawn => "",
+        }
+    }
+}
+
 /// A chess piece
 #[derive(Clone, Copy)]
 pub struct Piece {
@@ -77,9 +135,20 @@ pub struct Piece {
 impl Piece {
     /// Check if moving to the new position is valid.
     pub fn is_move_valid(&self, new_position: (u8, u8), pieces: &Vec<Piece>) -> bool {
+        self.reaches(new_position, color_of_square(new_position, pieces), pieces)
+    }
+
+    /// Check if moving to the new position would be valid, were it held by a piece of
+    /// the given color.
+    fn reaches(
+        &self,
+        new_position: (u8, u8),
+        occupant: Option<PieceColor>,
+        pieces: &Vec<Piece>,
+    ) -> bool {
         // Check not the same color
         // As a side effect, also guards we are not moving to the current position.
-        if Some(self.color) == color_of_square(new_position, &pieces) {
+        if Some(self.color) == occupant {
             return false;
         }
 
@@ -114,7 +183,7 @@ impl Piece {
                 } else {
                     dist
                 };
-                let square_color = color_of_square(new_position, &pieces);
+                let square_color = occupant;
 
                 // A pawn cannot move backward.
 
@@ -152,6 +221,130 @@ impl Piece {
     }
 }
 
+/// Check if a piece of the given color could capture on the square.
+/// The square must hold a piece of the other color, as pawns only take on occupied squares.
+pub fn is_attacked(square: (u8, u8), by: PieceColor, pieces: &Vec<Piece>) -> bool {
+    pieces
+        .iter()
+        .any(|piece| piece.color == by && piece.is_move_valid(square, pieces))
+}
+
+/// Check if a piece is attacked and not defended.
+pub fn is_hanging(piece: &Piece, pieces: &Vec<Piece>) -> bool {
+    let square = (piece.x, piece.y);
+    if !is_attacked(square, piece.color.opponent(), pieces) {
+        return false;
+    }
+
+    // Defenders are the pieces that could take back if the piece was taken
+    !pieces.iter().any(|p| {
+        p.color == piece.color
+            && (p.x, p.y) != square
+            && p.reaches(square, Some(piece.color.opponent()), pieces)
+    })
+}
+
+/// Shake of a piece refusing an illegal move.
+pub struct Shake {
+    timer: Timer,
+}
+
+impl Default for Shake {
+    fn default() -> Self {
+        Self {
+            timer: Timer::from_seconds(0.4, false),
+        }
+    }
+}
+
+/// Fall of a taken king, tipping over on its way to the graveyard.
+pub struct Topple {
+    timer: Timer,
+}
+
+impl Default for Topple {
+    fn default() -> Self {
+        Self {
+            timer: Timer::from_seconds(0.8, false),
+        }
+    }
+}
+
+/// A piece taken out of the game, resting in the graveyard.
+pub struct Captured {
+    /// The piece as it was when taken
+    pub piece: Piece,
+    /// Place in the graveyard row of its color
+    pub slot: usize,
+}
+
+impl Captured {
+    /// Position of the graveyard slot, in a row along the side of the board.
+    pub fn translation(&self) -> Vec3 {
+        let z = match self.piece.color {
+            PieceColor::White => -1.2,
+            PieceColor::Black => 8.2,
+        };
+        Vec3::new(self.slot as f32 * 0.5, 0.0, z)
+    }
+}
+
+/// Map between piece entities and the square they stand on.
+#[derive(Default)]
+pub struct PieceIndex {
+    by_square: HashMap<(u8, u8), Entity>,
+    by_entity: HashMap<Entity, (u8, u8)>,
+}
+
+impl PieceIndex {
+    /// Piece standing on the given square.
+    pub fn at(&self, square: (u8, u8)) -> Option<Entity> {
+        self.by_square.get(&square).copied()
+    }
+
+    /// Square the given piece stands on.
+    pub fn square_of(&self, entity: Entity) -> Option<(u8, u8)> {
+        self.by_entity.get(&entity).copied()
+    }
+
+    /// Put a piece on a square, dropping any other piece from that square.
+    pub fn insert(&mut self, entity: Entity, square: (u8, u8)) {
+        self.remove(entity);
+        if let Some(other) = self.by_square.insert(square, entity) {
+            self.by_entity.remove(&other);
+        }
+        self.by_entity.insert(entity, square);
+    }
+
+    /// Forget a piece.
+    pub fn remove(&mut self, entity: Entity) {
+        if let Some(square) = self.by_entity.remove(&entity) {
+            if self.by_square.get(&square) == Some(&entity) {
+                self.by_square.remove(&square);
+            }
+        }
+    }
+}
+
+/// Pieces on the board, copied out of the world for move validation.
+///
+/// Refilled in place once the commands of a frame have been applied, so that
+/// validating moves against it does not allocate once the buffer has grown to
+/// the starting position. Systems running after `play_move` see the position
+/// before the move until the next frame.
+#[derive(Default)]
+pub struct BoardState {
+    pub pieces: Vec<Piece>,
+}
+
+impl BoardState {
+    /// Replace the pieces, reusing the buffer.
+    pub fn refill(&mut self, pieces: impl IntoIterator<Item = Piece>) {
+        self.pieces.clear();
+        self.pieces.extend(pieces);
+    }
+}
+
 /// Manhatan distance.
 #[derive(Clone, Copy, PartialEq, Eq)]
 struct Manhatan(i8, i8);
@@ -244,245 +437,321 @@ fn is_path_empty((x1, y1): (u8, u8), (x2, y2): (u8, u8), pieces: &Vec<Piece>) ->
     true
 }
 
-fn create_pieces(
+/// Spawn the model of a piece type on a square, without any game component.
+pub(crate) fn spawn_model(
+    commands: &mut Commands,
+    assets: &ChessAssets,
+    piece_type: PieceType,
+    material: Handle<StandardMaterial>,
+    (x, y): (u8, u8),
+) -> Entity {
+    commands
+        .spawn_bundle(PbrBundle {
+            transform: Transform::from_translation(Vec3::new(x as f32, 0.0, y as f32)),
+            ..Default::default()
+        })
+        .with_children(|parent| spawn_meshes(parent, assets, piece_type, material))
+        .id()
+}
+
+/// Mesh of a piece model, scaled with the piece size setting
+struct PieceMesh(PieceType);
+
+/// Spawn the meshes of a piece model as children of its entity.
+fn spawn_meshes(
+    parent: &mut ChildBuilder,
+    assets: &ChessAssets,
+    piece_type: PieceType,
+    material: Handle<StandardMaterial>,
+) {
+    let model = assets.model(piece_type);
+    let is_transparent = assets.is_translucent_material(&material);
+
+    for mesh in model.meshes.iter() {
+        let mut entity = parent.spawn_bundle(PbrBundle {
+            mesh: mesh.clone(),
+            material: material.clone(),
+            transform: model.transform,
+            visible: Visible {
+                is_visible: true,
+                is_transparent,
+            },
+            ..Default::default()
+        });
+        entity.insert(PieceMesh(piece_type));
+        // Clicking a piece selects its square, but ghosts must not hide the square below
+        if !is_transparent {
+            entity.insert_bundle(PickableBundle::default());
+        }
+    }
+}
+
+/// Scale the piece models with the piece size setting.
+fn resize_pieces(
+    assets: Res<ChessAssets>,
+    settings: Res<Settings>,
+    mut query: Query<(&PieceMesh, &mut Transform)>,
+) {
+    let factor = settings.piece_size.factor();
+    for (mesh, mut transform) in query.iter_mut() {
+        let model = assets.model(mesh.0).transform;
+        // Scaled around the center of the square
+        let resized = Transform {
+            translation: model.translation * factor,
+            rotation: model.rotation,
+            scale: model.scale * factor,
+        };
+        // Avoid touching resized meshes so their transforms are not marked as changed
+        if *transform != resized {
+            *transform = resized;
+        }
+    }
+}
+
+/// Respawn the meshes of pieces when the piece kit is reloaded.
+fn reload_models(
     mut commands: Commands,
-    asset_server: ResMut<AssetServer>,
-    mut materials: ResMut<Assets<StandardMaterial>>,
+    assets: Res<ChessAssets>,
+    query: Query<
+        (Entity, &Children, Option<&Piece>, Option<&Captured>),
+        Or<(With<Piece>, With<Captured>)>,
+    >,
 ) {
-    let king_handle: Handle<Mesh> =
-        asset_server.load("models/chess_kit/pieces.glb#Mesh0/Primitive0");
-    let king_cross_handle: Handle<Mesh> =
-        asset_server.load("models/chess_kit/pieces.glb#Mesh1/Primitive0");
-    let pawn_handle: Handle<Mesh> =
-        asset_server.load("models/chess_kit/pieces.glb#Mesh2/Primitive0");
-    let knight_1_handle: Handle<Mesh> =
-        asset_server.load("models/chess_kit/pieces.glb#Mesh3/Primitive0");
-    let knight_2_handle: Handle<Mesh> =
-        asset_server.load("models/chess_kit/pieces.glb#Mesh4/Primitive0");
-    let rook_handle: Handle<Mesh> =
-        asset_server.load("models/chess_kit/pieces.glb#Mesh5/Primitive0");
-    let bishop_handle: Handle<Mesh> =
-        asset_server.load("models/chess_kit/pieces.glb#Mesh6/Primitive0");
-    let queen_handle: Handle<Mesh> =
-        asset_server.load("models/chess_kit/pieces.glb#Mesh7/Primitive0");
-
-    let white_material = materials.add(Color::rgb(1.0, 0.8, 0.8).into());
-    let black_material = materials.add(Color::rgb(0.0, 0.2, 0.2).into());
-
-    // Rook
-    spawn_piece!(
-        commands,
-        (0, 0),
-        Vec3::new(-0.1, 0.0, 1.8),
-        PieceType::Rook,
-        PieceColor::White,
-        white_material.clone(),
-        rook_handle.clone(),
-    );
-
-    // Knight
-    spawn_piece!(
-        commands,
-        (0, 1),
-        Vec3::new(-0.2, 0.0, 0.9),
-        PieceType::Knight,
-        PieceColor::White,
-        white_material.clone(),
-        knight_1_handle.clone(),
-        knight_2_handle.clone(),
-    );
-
-    // Bishop
-    spawn_piece!(
-        commands,
-        (0, 2),
-        Vec3::new(-0.1, 0.0, 0.0),
-        PieceType::Bishop,
-        PieceColor::White,
-        white_material.clone(),
-        bishop_handle.clone(),
-    );
-
-    // Queen
-    spawn_piece!(
-        commands,
-        (0, 3),
-        Vec3::new(-0.2, 0.0, -0.95),
-        PieceType::Queen,
-        PieceColor::White,
-        white_material.clone(),
-        queen_handle.clone(),
-    );
-
-    // Spawn King
-    spawn_piece!(
-        commands,
-        (0, 4),
-        Vec3::new(-0.2, 0.0, -1.9),
-        PieceType::King,
-        PieceColor::White,
-        white_material.clone(),
-        king_handle.clone(),
-        king_cross_handle.clone(),
-    );
-
-    // Bishop
-    spawn_piece!(
-        commands,
-        (0, 5),
-        Vec3::new(-0.1, 0.0, 0.0),
-        PieceType::Bishop,
-        PieceColor::White,
-        white_material.clone(),
-        bishop_handle.clone(),
-    );
-
-    // Knight
-    spawn_piece!(
-        commands,
-        (0, 6),
-        Vec3::new(-0.2, 0.0, 0.9),
-        PieceType::Knight,
-        PieceColor::White,
-        white_material.clone(),
-        knight_1_handle.clone(),
-        knight_2_handle.clone(),
-    );
-
-    // Rook
-    spawn_piece!(
-        commands,
-        (0, 7),
-        Vec3::new(-0.1, 0.0, 1.8),
-        PieceType::Rook,
-        PieceColor::White,
-        white_material.clone(),
-        rook_handle.clone(),
-    );
-
-    // Pawn
-    for i in 0..8 {
-        spawn_piece!(
+    if !assets.is_changed() {
+        return;
+    }
+
+    for (entity, children, piece, captured) in query.iter() {
+        let piece = match (piece, captured) {
+            (Some(piece), _) => piece,
+            (None, Some(captured)) => &captured.piece,
+            (None, None) => unreachable!(),
+        };
+        for child in children.iter() {
+            commands.entity(*child).despawn_recursive();
+        }
+        commands.entity(entity).with_children(|parent| {
+            spawn_meshes(
+                parent,
+                &assets,
+                piece.piece_type,
+                assets.material(piece.color),
+            )
+        });
+    }
+}
+
+/// Spawn the given pieces on the board.
+pub(crate) fn spawn_pieces(commands: &mut Commands, assets: &ChessAssets, pieces: &[Piece]) {
+    let span = info_span!("spawn_pieces", count = pieces.len());
+    let _enter = span.enter();
+    let start = Instant::now();
+    for piece in pieces {
+        let entity = spawn_model(
             commands,
-            (1, i),
-            Vec3::new(-0.2, 0.0, 2.6),
-            PieceType::Pawn,
-            PieceColor::White,
-            white_material.clone(),
-            pawn_handle.clone(),
+            assets,
+            piece.piece_type,
+            assets.material(piece.color),
+            (piece.x, piece.y),
         );
+        commands.entity(entity).insert(*piece);
     }
+    debug!("spawned {} pieces in {:?}", pieces.len(), start.elapsed());
+}
 
-    // Rook
-    spawn_piece!(
-        commands,
-        (7, 0),
-        Vec3::new(-0.1, 0.0, 1.8),
-        PieceType::Rook,
-        PieceColor::Black,
-        black_material.clone(),
-        rook_handle.clone(),
-    );
-
-    // Knight
-    spawn_piece!(
-        commands,
-        (7, 1),
-        Vec3::new(-0.2, 0.0, 0.9),
-        PieceType::Knight,
-        PieceColor::Black,
-        black_material.clone(),
-        knight_1_handle.clone(),
-        knight_2_handle.clone(),
-    );
-
-    // Bishop
-    spawn_piece!(
-        commands,
-        (7, 2),
-        Vec3::new(-0.1, 0.0, 0.0),
-        PieceType::Bishop,
-        PieceColor::Black,
-        black_material.clone(),
-        bishop_handle.clone(),
-    );
-
-    // Queen
-    spawn_piece!(
-        commands,
-        (7, 3),
-        Vec3::new(-0.2, 0.0, -0.95),
-        PieceType::Queen,
-        PieceColor::Black,
-        black_material.clone(),
-        queen_handle.clone(),
-    );
-
-    // Spawn King
-    spawn_piece!(
-        commands,
-        (7, 4),
-        Vec3::new(-0.2, 0.0, -1.9),
-        PieceType::King,
-        PieceColor::Black,
-        black_material.clone(),
-        king_handle.clone(),
-        king_cross_handle.clone(),
-    );
-
-    // Bishop
-    spawn_piece!(
-        commands,
-        (7, 5),
-        Vec3::new(-0.1, 0.0, 0.0),
-        PieceType::Bishop,
-        PieceColor::Black,
-        black_material.clone(),
-        bishop_handle.clone(),
-    );
-
-    // Knight
-    spawn_piece!(
-        commands,
-        (7, 6),
-        Vec3::new(-0.2, 0.0, 0.9),
-        PieceType::Knight,
-        PieceColor::Black,
-        black_material.clone(),
-        knight_1_handle.clone(),
-        knight_2_handle.clone(),
-    );
-
-    // Rook
-    spawn_piece!(
-        commands,
-        (7, 7),
-        Vec3::new(-0.1, 0.0, 1.8),
-        PieceType::Rook,
-        PieceColor::Black,
-        black_material.clone(),
-        rook_handle.clone(),
-    );
-
-    // Pawn
-    for i in 0..8 {
-        spawn_piece!(
-            commands,
-            (6, i),
-            Vec3::new(-0.2, 0.0, 2.6),
-            PieceType::Pawn,
-            PieceColor::Black,
-            black_material.clone(),
-            pawn_handle.clone(),
+fn create_pieces(mut commands: Commands, assets: Res<ChessAssets>, config: Res<GameConfig>) {
+    let position = config.starting_position();
+    spawn_pieces(&mut commands, &assets, &position.pieces);
+}
+
+/// Replace all pieces on the board with the loaded position.
+fn load_position(
+    mut commands: Commands,
+    assets: Res<ChessAssets>,
+    mut load_position_events: EventReader<LoadPositionEvent>,
+    query: Query<Entity, Or<(With<Piece>, With<Captured>)>>,
+) {
+    if let Some(event) = load_position_events.iter().last() {
+        for entity in query.iter() {
+            commands.entity(entity).despawn_recursive();
+        }
+        spawn_pieces(&mut commands, &assets, &event.position.pieces);
+    }
+}
+
+/// Keep the piece index in sync with spawned and removed pieces.
+fn index_pieces(
+    mut index: ResMut<PieceIndex>,
+    removed: RemovedComponents<Piece>,
+    added_query: Query<(Entity, &Piece), Added<Piece>>,
+) {
+    for entity in removed.iter() {
+        index.remove(entity);
+    }
+    for (entity, piece) in added_query.iter() {
+        index.insert(entity, (piece.x, piece.y));
+    }
+}
+
+/// Refill the board state when pieces move, are taken or are removed.
+fn update_board_state(
+    mut board: ResMut<BoardState>,
+    removed: RemovedComponents<Piece>,
+    changed_query: Query<(), Or<(Changed<Piece>, Added<Taken>)>>,
+    pieces_query: Query<&Piece, Without<Taken>>,
+) {
+    if changed_query.iter().next().is_none() && removed.iter().next().is_none() {
+        return;
+    }
+    board.refill(pieces_query.iter().copied());
+}
+
+/// Move a translation towards its target at the given speed, or jump there if `None`.
+fn move_towards(translation: &mut Vec3, target: Vec3, speed: Option<f32>, delta: f32) {
+    let direction = target - *translation;
+    let distance = direction.length();
+    if distance <= ARRIVAL_DISTANCE {
+        return;
+    }
+    match speed {
+        Some(speed) => *translation += direction / distance * (speed * delta).min(distance),
+        None => *translation = target,
+    }
+}
+
+fn move_pieces(
+    time: Res<Time>,
+    settings: Res<Settings>,
+    mut query: Query<(&mut Transform, &Piece)>,
+) {
+    let speed = settings.piece_speed();
+    for (mut transform, piece) in query.iter_mut() {
+        let target = Vec3::new(piece.x as f32, 0.0, piece.y as f32);
+        move_towards(
+            &mut transform.translation,
+            target,
+            speed,
+            time.delta_seconds(),
         );
     }
 }
 
-fn move_pieces(time: Res<Time>, mut query: Query<(&mut Transform, &Piece)>) {
+/// Slide captured pieces off the board into the graveyard.
+fn move_captured_pieces(
+    time: Res<Time>,
+    settings: Res<Settings>,
+    mut query: Query<(&mut Transform, &Captured)>,
+) {
+    // Faster than the moving piece, to clear its destination square
+    let speed = settings.piece_speed().map(|speed| speed * 4.0);
+    for (mut transform, captured) in query.iter_mut() {
+        move_towards(
+            &mut transform.translation,
+            captured.translation(),
+            speed,
+            time.delta_seconds(),
+        );
+    }
+}
+
+/// Lock input until the pieces of a move reach their destination.
+fn start_animation(
+    mut state: ResMut<State<AppState>>,
+    mut move_events: EventReader<MoveEvent>,
+) -> Result<(), ChessError> {
+    if move_events.iter().count() > 0 && *state.current() == AppState::Playing {
+        state.push(AppState::Animating)?;
+    }
+    Ok(())
+}
+
+/// Give input back once all pieces are on their square or in the graveyard.
+fn end_animation(
+    mut state: ResMut<State<AppState>>,
+    pieces_query: Query<(&Transform, &Piece)>,
+    captured_query: Query<(&Transform, &Captured)>,
+) -> Result<(), ChessError> {
+    let pieces_moving = pieces_query.iter().any(|(transform, piece)| {
+        let target = Vec3::new(piece.x as f32, 0.0, piece.y as f32);
+        transform.translation.distance(target) > ARRIVAL_DISTANCE
+    });
+    let captured_moving = captured_query.iter().any(|(transform, captured)| {
+        transform.translation.distance(captured.translation()) > ARRIVAL_DISTANCE
+    });
+
+    if !pieces_moving && !captured_moving {
+        state.pop()?;
+    }
+    Ok(())
+}
+
+/// Rock shaking pieces from side to side, as if saying no.
+fn shake_pieces(
+    mut commands: Commands,
+    time: Res<Time>,
+    settings: Res<Settings>,
+    mut query: Query<(Entity, &mut Transform, &mut Shake)>,
+) {
+    for (entity, mut transform, mut shake) in query.iter_mut() {
+        shake.timer.tick(time.delta());
+        // The illegal move is also reported in the status message
+        if shake.timer.finished() || settings.reduced_motion {
+            transform.rotation = Quat::IDENTITY;
+            commands.entity(entity).remove::<Shake>();
+        } else {
+            let angle = (shake.timer.percent() * 6.0 * std::f32::consts::PI).sin()
+                * 0.15
+                * shake.timer.percent_left();
+            transform.rotation = Quat::from_rotation_x(angle);
+        }
+    }
+}
+
+/// Tip toppling pieces over, falling faster and faster, with a small bounce at the end.
+fn topple_pieces(
+    time: Res<Time>,
+    settings: Res<Settings>,
+    mut query: Query<(&mut Transform, &mut Topple)>,
+) {
+    for (mut transform, mut topple) in query.iter_mut() {
+        if topple.timer.finished() {
+            continue;
+        }
+        if settings.reduced_motion {
+            let duration = topple.timer.duration();
+            topple.timer.set_elapsed(duration);
+        }
+        topple.timer.tick(time.delta());
+
+        let t = topple.timer.percent();
+        // Falls in the first 80% of the time, then bounces once on the board
+        let fall = if t < 0.8 {
+            (t / 0.8).powi(2)
+        } else {
+            1.0 - 0.08 * ((t - 0.8) / 0.2 * std::f32::consts::PI).sin()
+        };
+        transform.rotation = Quat::from_rotation_z(fall * std::f32::consts::FRAC_PI_2);
+    }
+}
+
+/// Slowly breathe the pieces of the player to move, to show whose turn it is.
+fn breathe_pieces(
+    time: Res<Time>,
+    turn: Res<PlayerTurn>,
+    settings: Res<Settings>,
+    mut query: Query<(&mut Transform, &Piece)>,
+) {
+    let phase = time.seconds_since_startup() as f32 / BREATH_PERIOD * std::f32::consts::TAU;
+    let breath = Vec3::splat(1.0 + BREATH_SCALE * (0.5 - 0.5 * phase.cos()));
     for (mut transform, piece) in query.iter_mut() {
-        let direction = Vec3::new(piece.x as f32, 0.0, piece.y as f32) - transform.translation;
-        if direction.length() > 0.1 {
-            transform.translation += direction.normalize() * time.delta_seconds();
+        let scale = if piece.color == turn.0 && !settings.reduced_motion {
+            breath
+        } else {
+            Vec3::ONE
+        };
+        // Avoid touching resting pieces so their transforms are not marked as changed
+        if transform.scale != scale {
+            transform.scale = scale;
         }
     }
 }
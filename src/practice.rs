@@ -0,0 +1,432 @@
+use bevy::prelude::*;
+
+use crate::board::{GameOverEvent, LoadPositionEvent, MoveEvent};
+use crate::fen::parse_fen;
+use crate::locale::*;
+use crate::pieces::PieceColor;
+use crate::settings::Settings;
+use crate::status::StatusMessage;
+use crate::ui::UiAssets;
+
+/// A practice position.
+pub struct Preset {
+    /// Locale key of the name shown in the menu
+    pub name: &'static str,
+    pub fen: &'static str,
+    /// Locale key of the goal shown in the banner, `{}` standing for the
+    /// number of moves to hold
+    pub goal: &'static str,
+    pub objective: Objective,
+}
+
+/// What solves a practice position.
+#[derive(Clone, Copy)]
+pub enum Objective {
+    /// Take the king of the opponent
+    Win(PieceColor),
+    /// Keep the king for the given number of moves of the side, as draws are
+    /// not detected
+    Hold(PieceColor, u32),
+}
+
+impl Objective {
+    /// Side trying to reach the objective.
+    fn side(self) -> PieceColor {
+        match self {
+            Objective::Win(color) | Objective::Hold(color, _) => color,
+        }
+    }
+
+    /// Moves to survive, for `Hold`.
+    fn moves_to_hold(self) -> Option<u32> {
+        match self {
+            Objective::Win(_) => None,
+            Objective::Hold(_, moves) => Some(moves),
+        }
+    }
+}
+
+/// Common endgames to practice.
+pub const PRESETS: &[Preset] = &[
+    Preset {
+        name: "preset_kq_k",
+        fen: "8/8/8/4k3/8/8/8/3QK3 w - - 0 1",
+        goal: "goal_kq_k",
+        objective: Objective::Win(PieceColor::White),
+    },
+    Preset {
+        name: "preset_kr_k",
+        fen: "8/8/8/4k3/8/8/8/R3K3 w - - 0 1",
+        goal: "goal_kr_k",
+        objective: Objective::Win(PieceColor::White),
+    },
+    Preset {
+        name: "preset_lucena",
+        fen: "1K1k4/1P6/8/8/8/8/r7/2R5 w - - 0 1",
+        goal: "goal_lucena",
+        objective: Objective::Win(PieceColor::White),
+    },
+    Preset {
+        name: "preset_philidor",
+        fen: "4k3/8/r7/4PK2/8/8/8/1R6 b - - 0 1",
+        goal: "goal_philidor",
+        objective: Objective::Hold(PieceColor::Black, 15),
+    },
+    Preset {
+        name: "preset_r_b",
+        fen: "8/8/4k3/8/2b5/8/4K3/3R4 w - - 0 1",
+        goal: "goal_r_b",
+        objective: Objective::Hold(PieceColor::Black, 20),
+    },
+];
+
+/// Button toggling the practice menu
+struct PracticeButton;
+
+/// Root of the practice menu
+struct PracticeMenu;
+
+/// Button loading the preset at the given index
+struct PresetButton(usize);
+
+/// Progress towards the objective of a practice position.
+#[derive(Clone, Copy, PartialEq)]
+enum ObjectiveState {
+    Pending,
+    Solved,
+    Failed,
+}
+
+/// Practice position being played, if any.
+struct CurrentObjective {
+    /// Index of the preset being played
+    preset: Option<usize>,
+    /// Index of the preset whose position is being loaded
+    loading: Option<usize>,
+    /// Moves played by the side of the objective since the preset was loaded
+    moves: u32,
+    state: ObjectiveState,
+}
+
+impl Default for CurrentObjective {
+    fn default() -> Self {
+        Self {
+            preset: None,
+            loading: None,
+            moves: 0,
+            state: ObjectiveState::Pending,
+        }
+    }
+}
+
+/// Banner at the top of the window showing the goal of the current preset
+struct ObjectiveBanner;
+
+/// Text showing the goal of the current preset
+struct GoalText;
+
+/// Initialize practice button and objective banner
+fn init_practice_ui(mut commands: Commands, assets: Res<UiAssets>) {
+    commands
+        .spawn_bundle(ButtonBundle {
+            style: Style {
+                position_type: PositionType::Absolute,
+                position: Rect {
+                    left: Val::Px(10.0),
+                    bottom: Val::Px(10.0),
+                    ..Default::default()
+                },
+                padding: Rect::all(Val::Px(8.0)),
+                ..Default::default()
+            },
+            material: assets.button.clone(),
+            ..Default::default()
+        })
+        .insert(PracticeButton)
+        .with_children(|parent| {
+            parent
+                .spawn_bundle(TextBundle {
+                    text: assets.text(tr(Language::default(), "practice"), 30.0),
+                    ..Default::default()
+                })
+                .insert(Localized("practice"));
+        });
+
+    // Below the compass
+    commands
+        .spawn_bundle(NodeBundle {
+            style: Style {
+                position_type: PositionType::Absolute,
+                position: Rect {
+                    left: Val::Px(0.0),
+                    right: Val::Px(0.0),
+                    top: Val::Px(100.0),
+                    ..Default::default()
+                },
+                justify_content: JustifyContent::Center,
+                ..Default::default()
+            },
+            material: assets.transparent.clone(),
+            ..Default::default()
+        })
+        .with_children(|parent| {
+            parent
+                .spawn_bundle(NodeBundle {
+                    style: Style {
+                        display: Display::None,
+                        padding: Rect::all(Val::Px(8.0)),
+                        ..Default::default()
+                    },
+                    material: assets.panel.clone(),
+                    ..Default::default()
+                })
+                .insert(ObjectiveBanner)
+                .with_children(|parent| {
+                    parent
+                        .spawn_bundle(TextBundle {
+                            text: assets.text("", 24.0),
+                            visible: Visible {
+                                is_visible: false,
+                                ..Default::default()
+                            },
+                            ..Default::default()
+                        })
+                        .insert(GoalText);
+                });
+        });
+}
+
+/// Open or close the practice menu
+fn toggle_practice_menu(
+    mut commands: Commands,
+    assets: Res<UiAssets>,
+    settings: Res<Settings>,
+    button_query: Query<&Interaction, (Changed<Interaction>, With<PracticeButton>)>,
+    menu_query: Query<Entity, With<PracticeMenu>>,
+) {
+    if !button_query.iter().any(|i| *i == Interaction::Clicked) {
+        return;
+    }
+
+    // Close the menu if already opened
+    if let Some(menu) = menu_query.iter().next() {
+        commands.entity(menu).despawn_recursive();
+        return;
+    }
+
+    commands
+        .spawn_bundle(NodeBundle {
+            style: Style {
+                position_type: PositionType::Absolute,
+                position: Rect {
+                    left: Val::Px(10.0),
+                    bottom: Val::Px(70.0),
+                    ..Default::default()
+                },
+                // Reversed as UI nodes are laid out from the bottom
+                flex_direction: FlexDirection::ColumnReverse,
+                padding: Rect::all(Val::Px(4.0)),
+                ..Default::default()
+            },
+            material: assets.panel.clone(),
+            ..Default::default()
+        })
+        .insert(PracticeMenu)
+        .with_children(|parent| {
+            for (i, preset) in PRESETS.iter().enumerate() {
+                assets.spawn_localized_button(
+                    parent,
+                    settings.language,
+                    preset.name,
+                    24.0,
+                    PresetButton(i),
+                );
+            }
+        });
+}
+
+/// Load the clicked preset and show its goal
+fn select_preset(
+    mut commands: Commands,
+    mut load_position_events: EventWriter<LoadPositionEvent>,
+    settings: Res<Settings>,
+    mut status_messages: EventWriter<StatusMessage>,
+    mut objective: ResMut<CurrentObjective>,
+    button_query: Query<(&Interaction, &PresetButton), Changed<Interaction>>,
+    menu_query: Query<Entity, With<PracticeMenu>>,
+) {
+    for (interaction, preset_button) in button_query.iter() {
+        if *interaction != Interaction::Clicked {
+            continue;
+        }
+
+        let preset = &PRESETS[preset_button.0];
+        match parse_fen(preset.fen) {
+            Ok(position) => {
+                load_position_events.send(LoadPositionEvent { position });
+                objective.loading = Some(preset_button.0);
+            }
+            Err(err) => {
+                warn!("invalid preset {}: {}", preset.name, err);
+                status_messages.send(StatusMessage(tr_with(
+                    settings.language,
+                    "cannot_load",
+                    tr(settings.language, preset.name),
+                )));
+            }
+        }
+
+        for menu in menu_query.iter() {
+            commands.entity(menu).despawn_recursive();
+        }
+    }
+}
+
+/// Follow the objective of the preset being played, from the moves of its side
+/// and the end of the game. Positions loaded otherwise, e.g. pasted, leave
+/// practice.
+fn track_objective(
+    mut objective: ResMut<CurrentObjective>,
+    mut load_position_events: EventReader<LoadPositionEvent>,
+    mut move_events: EventReader<MoveEvent>,
+    mut game_over_events: EventReader<GameOverEvent>,
+) {
+    if load_position_events.iter().last().is_some() {
+        objective.preset = objective.loading.take();
+        objective.moves = 0;
+        objective.state = ObjectiveState::Pending;
+    }
+
+    let goal = match objective.preset {
+        Some(i) if objective.state == ObjectiveState::Pending => PRESETS[i].objective,
+        _ => return,
+    };
+    let side = goal.side();
+    if let Some(event) = game_over_events.iter().last() {
+        objective.state = if event.winner == side {
+            ObjectiveState::Solved
+        } else {
+            ObjectiveState::Failed
+        };
+        return;
+    }
+
+    let moves = move_events
+        .iter()
+        .filter(|event| event.piece.color == side)
+        .count() as u32;
+    if moves > 0 {
+        objective.moves += moves;
+        if goal
+            .moves_to_hold()
+            .map_or(false, |hold| objective.moves >= hold)
+        {
+            objective.state = ObjectiveState::Solved;
+        }
+    }
+}
+
+/// Show the goal of the preset being played, and whether it was reached
+fn objective_banner_update(
+    objective: Res<CurrentObjective>,
+    settings: Res<Settings>,
+    mut banner_query: Query<&mut Style, With<ObjectiveBanner>>,
+    mut text_query: Query<(&mut Text, &mut Visible), With<GoalText>>,
+) {
+    if !objective.is_changed() && !settings.is_changed() {
+        return;
+    }
+
+    let preset = objective.preset.map(|i| &PRESETS[i]);
+    for mut style in banner_query.iter_mut() {
+        style.display = if preset.is_some() {
+            Display::Flex
+        } else {
+            Display::None
+        };
+    }
+
+    let preset = match preset {
+        Some(preset) => preset,
+        None => {
+            for (_, mut visible) in text_query.iter_mut() {
+                visible.is_visible = false;
+            }
+            return;
+        }
+    };
+    let hold = preset.objective.moves_to_hold();
+    let goal = tr_with(
+        settings.language,
+        preset.goal,
+        &hold.unwrap_or(0).to_string(),
+    );
+    let (value, color) = match (objective.state, hold) {
+        (ObjectiveState::Pending, Some(hold)) => (
+            format!(
+                "{} - {}",
+                goal,
+                tr_args(
+                    settings.language,
+                    "moves_held",
+                    &[&objective.moves.to_string(), &hold.to_string()],
+                )
+            ),
+            Color::rgb(0.8, 0.8, 0.8),
+        ),
+        (ObjectiveState::Pending, None) => (goal, Color::rgb(0.8, 0.8, 0.8)),
+        (ObjectiveState::Solved, _) => (
+            format!("{} - {}", goal, tr(settings.language, "solved")),
+            Color::rgb(0.3, 0.8, 0.3),
+        ),
+        (ObjectiveState::Failed, _) => (
+            format!("{} - {}", goal, tr(settings.language, "failed")),
+            Color::rgb(0.9, 0.3, 0.3),
+        ),
+    };
+    for (mut text, mut visible) in text_query.iter_mut() {
+        text.sections[0].value = value.clone();
+        text.sections[0].style.color = color;
+        visible.is_visible = true;
+    }
+}
+
+/// Practice Plugin
+pub struct PracticePlugin;
+
+impl Plugin for PracticePlugin {
+    fn build(&self, app: &mut AppBuilder) {
+        app.init_resource::<CurrentObjective>()
+            .add_startup_system(init_practice_ui.system())
+            .add_system(toggle_practice_menu.system())
+            .add_system(select_preset.system())
+            .add_system(track_objective.system())
+            .add_system(objective_banner_update.system());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn presets_are_valid_and_translated() {
+        for preset in PRESETS {
+            assert!(parse_fen(preset.fen).is_ok(), "{}", preset.name);
+            for &language in Language::ALL {
+                assert_ne!(tr(language, preset.name), preset.name);
+                assert_ne!(tr(language, preset.goal), preset.goal);
+            }
+        }
+    }
+
+    #[test]
+    fn hold_presets_can_be_solved_by_moves() {
+        for preset in PRESETS {
+            if let Objective::Hold(_, moves) = preset.objective {
+                assert!(moves > 0, "{}", preset.name);
+                assert!(tr(Language::English, preset.goal).contains("{}"));
+            }
+        }
+    }
+}
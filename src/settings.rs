@@ -0,0 +1,741 @@
+use bevy::prelude::*;
+
+use crate::locale::*;
+use crate::ui::UiAssets;
+
+/// Player preferences.
+#[derive(Default)]
+pub struct Settings {
+    /// Require a confirmation before a move is played
+    pub confirm_moves: bool,
+    /// Show the name and square of each piece above it
+    pub piece_labels: bool,
+    /// Push the camera in on the taken king at the end of the game
+    pub camera_cuts: bool,
+    /// Orbit the board and frame moves while nobody touches the game, for spectators
+    pub auto_camera: bool,
+    /// Replace camera moves, piece animations and effects by instant changes,
+    /// for players sensitive to motion
+    pub reduced_motion: bool,
+    /// Show how many moves each piece of the player to move has
+    pub move_counts: bool,
+    /// Mark the pieces of the player to move that are attacked and not defended
+    pub show_threats: bool,
+    /// Fade all pieces but the selected one, those it can take and the attackers
+    /// of the king, to read busy positions
+    pub focus_mode: bool,
+    /// Play a sound on selection, refused moves, check and each turn, to follow
+    /// the game by ear
+    pub audio_cues: bool,
+    /// Keep the clock from ticking during time scramble
+    pub silent_clock: bool,
+    /// Speed of the pieces moving on the board
+    pub animation_speed: AnimationSpeed,
+    /// Size of the pieces on their squares
+    pub piece_size: PieceSize,
+    /// Gap left between the squares of the board
+    pub board_gap: BoardGap,
+    /// How moves are written in the history
+    pub notation: NotationStyle,
+    /// How the clocks show the remaining time
+    pub clock_style: ClockStyle,
+    /// Where the clocks are shown
+    pub clock_placement: ClockPlacement,
+    /// Width of the move history panel, `None` to fit the moves
+    pub history_width: Option<f32>,
+    /// Hide the moves in the history panel, leaving more room for the board
+    pub history_collapsed: bool,
+    /// Keys of the keyboard shortcuts, e.g. to play with one hand
+    pub key_profile: KeyProfile,
+    /// Language of the UI
+    pub language: Language,
+}
+
+impl Settings {
+    /// Speed of moving pieces in squares per second, `None` when they move instantly.
+    pub fn piece_speed(&self) -> Option<f32> {
+        if self.reduced_motion {
+            return None;
+        }
+        self.animation_speed.squares_per_second()
+    }
+}
+
+/// Speed of the pieces moving on the board.
+#[derive(Clone, Copy, PartialEq)]
+pub enum AnimationSpeed {
+    Slow,
+    Normal,
+    Fast,
+    /// Pieces jump to their square, e.g. for bullet games
+    Instant,
+}
+
+impl Default for AnimationSpeed {
+    fn default() -> Self {
+        AnimationSpeed::Normal
+    }
+}
+
+impl AnimationSpeed {
+    /// All speeds, in picker order.
+    const ALL: &'static [AnimationSpeed] = &[
+        AnimationSpeed::Slow,
+        AnimationSpeed::Normal,
+        AnimationSpeed::Fast,
+        AnimationSpeed::Instant,
+    ];
+
+    /// Speed of moving pieces in squares per second, `None` when they move instantly.
+    pub fn squares_per_second(self) -> Option<f32> {
+        match self {
+            AnimationSpeed::Slow => Some(0.5),
+            AnimationSpeed::Normal => Some(1.0),
+            AnimationSpeed::Fast => Some(3.0),
+            AnimationSpeed::Instant => None,
+        }
+    }
+
+    /// Translation key of the speed.
+    fn label(self) -> &'static str {
+        match self {
+            AnimationSpeed::Slow => "slow",
+            AnimationSpeed::Normal => "normal",
+            AnimationSpeed::Fast => "fast",
+            AnimationSpeed::Instant => "instant",
+        }
+    }
+
+    /// Speed following this one in the picker.
+    fn next(self) -> AnimationSpeed {
+        let i = AnimationSpeed::ALL
+            .iter()
+            .position(|&speed| speed == self)
+            .unwrap_or(0);
+        AnimationSpeed::ALL[(i + 1) % AnimationSpeed::ALL.len()]
+    }
+}
+
+/// Size of the pieces, relative to the scale of the piece kit.
+#[derive(Clone, Copy, PartialEq)]
+pub enum PieceSize {
+    Small,
+    Normal,
+    Large,
+}
+
+impl Default for PieceSize {
+    fn default() -> Self {
+        PieceSize::Normal
+    }
+}
+
+impl PieceSize {
+    /// Factor applied to the scale of the piece kit.
+    pub fn factor(self) -> f32 {
+        match self {
+            PieceSize::Small => 0.8,
+            PieceSize::Normal => 1.0,
+            PieceSize::Large => 1.2,
+        }
+    }
+
+    /// Translation key of the size.
+    fn label(self) -> &'static str {
+        match self {
+            PieceSize::Small => "small",
+            PieceSize::Normal => "normal",
+            PieceSize::Large => "large",
+        }
+    }
+
+    /// Size following this one in the picker.
+    fn next(self) -> PieceSize {
+        match self {
+            PieceSize::Small => PieceSize::Normal,
+            PieceSize::Normal => PieceSize::Large,
+            PieceSize::Large => PieceSize::Small,
+        }
+    }
+}
+
+/// Gap left between the squares of the board.
+#[derive(Clone, Copy, PartialEq)]
+pub enum BoardGap {
+    None,
+    Thin,
+    Wide,
+}
+
+impl Default for BoardGap {
+    fn default() -> Self {
+        BoardGap::None
+    }
+}
+
+impl BoardGap {
+    /// Side of a square, squares being one unit apart.
+    pub fn square_size(self) -> f32 {
+        match self {
+            BoardGap::None => 1.0,
+            BoardGap::Thin => 0.95,
+            BoardGap::Wide => 0.88,
+        }
+    }
+
+    /// Translation key of the gap.
+    fn label(self) -> &'static str {
+        match self {
+            BoardGap::None => "no_gap",
+            BoardGap::Thin => "thin",
+            BoardGap::Wide => "wide",
+        }
+    }
+
+    /// Gap following this one in the picker.
+    fn next(self) -> BoardGap {
+        match self {
+            BoardGap::None => BoardGap::Thin,
+            BoardGap::Thin => BoardGap::Wide,
+            BoardGap::Wide => BoardGap::None,
+        }
+    }
+}
+
+/// Keys of the keyboard shortcuts, also listed by the help overlay.
+pub struct KeyBindings {
+    /// Move the square cursor away from the player
+    pub cursor_up: KeyCode,
+    /// Move the square cursor towards the player
+    pub cursor_down: KeyCode,
+    /// Move the square cursor to the left of the player
+    pub cursor_left: KeyCode,
+    /// Move the square cursor to the right of the player
+    pub cursor_right: KeyCode,
+    /// Select the square under the cursor
+    pub select: KeyCode,
+    /// Play the pending move
+    pub confirm: KeyCode,
+    /// Drop a typed file, or close the help
+    pub cancel: KeyCode,
+    /// Open or close the settings panel
+    pub menu: KeyCode,
+    /// Paste a FEN position, with Ctrl or Cmd
+    pub paste: KeyCode,
+    /// Show or hide the help
+    pub help: KeyCode,
+}
+
+impl Default for KeyBindings {
+    fn default() -> Self {
+        KeyProfile::default().bindings()
+    }
+}
+
+impl KeyBindings {
+    /// Controls of the game, with the translation key of what they do, in help order.
+    pub fn controls(&self) -> Vec<(String, &'static str)> {
+        vec![
+            (String::from("Mouse"), "help_click"),
+            (String::from("a1-h8"), "help_type_square"),
+            (
+                format!(
+                    "{:?}/{:?}/{:?}/{:?}",
+                    self.cursor_up, self.cursor_left, self.cursor_down, self.cursor_right
+                ),
+                "help_cursor",
+            ),
+            (format!("{:?}", self.select), "help_select"),
+            (format!("{:?}", self.cancel), "help_cancel"),
+            (format!("{:?}", self.confirm), "help_confirm"),
+            (format!("{:?}", self.menu), "help_menu"),
+            (format!("Ctrl+{:?}", self.paste), "help_paste"),
+            (format!("{:?}", self.help), "help_help"),
+        ]
+    }
+
+    /// Whether the key triggers an action, and so does not type a square.
+    pub fn is_bound(&self, key: KeyCode) -> bool {
+        [
+            self.cursor_up,
+            self.cursor_down,
+            self.cursor_left,
+            self.cursor_right,
+            self.select,
+            self.confirm,
+            self.cancel,
+            self.menu,
+            self.help,
+        ]
+        .contains(&key)
+    }
+}
+
+/// Set of key bindings, chosen in the settings panel.
+#[derive(Clone, Copy, PartialEq)]
+pub enum KeyProfile {
+    Standard,
+    /// Every action on the left half of the keyboard, leaving the file letters
+    /// and rank digits free to type squares
+    LeftHand,
+    /// Every action on the right half of the keyboard
+    RightHand,
+}
+
+impl Default for KeyProfile {
+    fn default() -> Self {
+        KeyProfile::Standard
+    }
+}
+
+impl KeyProfile {
+    /// Key bindings of the profile.
+    pub fn bindings(self) -> KeyBindings {
+        match self {
+            KeyProfile::Standard => KeyBindings {
+                cursor_up: KeyCode::Up,
+                cursor_down: KeyCode::Down,
+                cursor_left: KeyCode::Left,
+                cursor_right: KeyCode::Right,
+                select: KeyCode::Space,
+                confirm: KeyCode::Return,
+                cancel: KeyCode::Escape,
+                menu: KeyCode::F10,
+                paste: KeyCode::V,
+                help: KeyCode::F1,
+            },
+            KeyProfile::LeftHand => KeyBindings {
+                cursor_up: KeyCode::W,
+                cursor_down: KeyCode::S,
+                cursor_left: KeyCode::Q,
+                cursor_right: KeyCode::R,
+                select: KeyCode::Space,
+                confirm: KeyCode::LShift,
+                cancel: KeyCode::Escape,
+                menu: KeyCode::Tab,
+                paste: KeyCode::V,
+                help: KeyCode::F1,
+            },
+            KeyProfile::RightHand => KeyBindings {
+                cursor_up: KeyCode::Up,
+                cursor_down: KeyCode::Down,
+                cursor_left: KeyCode::Left,
+                cursor_right: KeyCode::Right,
+                select: KeyCode::RShift,
+                confirm: KeyCode::Return,
+                cancel: KeyCode::Back,
+                menu: KeyCode::F10,
+                paste: KeyCode::V,
+                help: KeyCode::F12,
+            },
+        }
+    }
+
+    /// Translation key of the profile.
+    fn label(self) -> &'static str {
+        match self {
+            KeyProfile::Standard => "standard",
+            KeyProfile::LeftHand => "left_hand",
+            KeyProfile::RightHand => "right_hand",
+        }
+    }
+
+    /// Profile following this one in the picker.
+    fn next(self) -> KeyProfile {
+        match self {
+            KeyProfile::Standard => KeyProfile::LeftHand,
+            KeyProfile::LeftHand => KeyProfile::RightHand,
+            KeyProfile::RightHand => KeyProfile::Standard,
+        }
+    }
+}
+
+/// How moves are written in the history.
+#[derive(Clone, Copy, PartialEq)]
+pub enum NotationStyle {
+    /// Standard algebraic notation, e.g. "Nf3"
+    San,
+    /// Long algebraic notation, e.g. "Ng1-f3"
+    Long,
+    /// Standard algebraic notation with piece glyphs, e.g. "♘f3"
+    Figurine,
+}
+
+impl Default for NotationStyle {
+    fn default() -> Self {
+        NotationStyle::Long
+    }
+}
+
+impl NotationStyle {
+    /// All styles, in picker order.
+    const ALL: &'static [NotationStyle] = &[
+        NotationStyle::San,
+        NotationStyle::Long,
+        NotationStyle::Figurine,
+    ];
+
+    /// Translation key of the style.
+    fn label(self) -> &'static str {
+        match self {
+            NotationStyle::San => "san",
+            NotationStyle::Long => "long",
+            NotationStyle::Figurine => "figurine",
+        }
+    }
+
+    /// Style following this one in the picker.
+    fn next(self) -> NotationStyle {
+        let i = NotationStyle::ALL
+            .iter()
+            .position(|&style| style == self)
+            .unwrap_or(0);
+        NotationStyle::ALL[(i + 1) % NotationStyle::ALL.len()]
+    }
+}
+
+/// How the clocks show the remaining time.
+#[derive(Clone, Copy, PartialEq)]
+pub enum ClockStyle {
+    Digital,
+    /// Dial with a hand reaching the flag when the time is up
+    Analog,
+}
+
+impl Default for ClockStyle {
+    fn default() -> Self {
+        ClockStyle::Digital
+    }
+}
+
+impl ClockStyle {
+    /// Translation key of the style.
+    fn label(self) -> &'static str {
+        match self {
+            ClockStyle::Digital => "digital",
+            ClockStyle::Analog => "analog",
+        }
+    }
+
+    /// Style following this one in the picker.
+    fn next(self) -> ClockStyle {
+        match self {
+            ClockStyle::Digital => ClockStyle::Analog,
+            ClockStyle::Analog => ClockStyle::Digital,
+        }
+    }
+}
+
+/// Where the clocks are shown.
+#[derive(Clone, Copy, PartialEq)]
+pub enum ClockPlacement {
+    /// Top left corner of the window
+    Corner,
+    /// Beside the board, following the camera
+    Board,
+}
+
+impl Default for ClockPlacement {
+    fn default() -> Self {
+        ClockPlacement::Corner
+    }
+}
+
+impl ClockPlacement {
+    /// Translation key of the placement.
+    fn label(self) -> &'static str {
+        match self {
+            ClockPlacement::Corner => "corner",
+            ClockPlacement::Board => "beside_board",
+        }
+    }
+
+    /// Placement following this one in the picker.
+    fn next(self) -> ClockPlacement {
+        match self {
+            ClockPlacement::Corner => ClockPlacement::Board,
+            ClockPlacement::Board => ClockPlacement::Corner,
+        }
+    }
+}
+
+/// Setting shown in the settings panel, changed by clicking it.
+#[derive(Clone, Copy)]
+enum Toggle {
+    ConfirmMoves,
+    PieceLabels,
+    CameraCuts,
+    AutoCamera,
+    ReducedMotion,
+    MoveCounts,
+    ShowThreats,
+    FocusMode,
+    AudioCues,
+    SilentClock,
+    AnimationSpeed,
+    PieceSize,
+    BoardGap,
+    Notation,
+    ClockStyle,
+    ClockPlacement,
+    KeyProfile,
+    Language,
+}
+
+impl Toggle {
+    /// All toggles, in panel order.
+    const ALL: &'static [Toggle] = &[
+        Toggle::ConfirmMoves,
+        Toggle::PieceLabels,
+        Toggle::CameraCuts,
+        Toggle::AutoCamera,
+        Toggle::ReducedMotion,
+        Toggle::MoveCounts,
+        Toggle::ShowThreats,
+        Toggle::FocusMode,
+        Toggle::AudioCues,
+        Toggle::SilentClock,
+        Toggle::AnimationSpeed,
+        Toggle::PieceSize,
+        Toggle::BoardGap,
+        Toggle::Notation,
+        Toggle::ClockStyle,
+        Toggle::ClockPlacement,
+        Toggle::KeyProfile,
+        Toggle::Language,
+    ];
+
+    /// Translation key of the label.
+    fn label(self) -> &'static str {
+        match self {
+            Toggle::ConfirmMoves => "confirm_moves",
+            Toggle::PieceLabels => "piece_labels",
+            Toggle::CameraCuts => "camera_cuts",
+            Toggle::AutoCamera => "auto_camera",
+            Toggle::ReducedMotion => "reduced_motion",
+            Toggle::MoveCounts => "move_counts",
+            Toggle::ShowThreats => "show_threats",
+            Toggle::FocusMode => "focus_mode",
+            Toggle::AudioCues => "audio_cues",
+            Toggle::SilentClock => "silent_clock",
+            Toggle::AnimationSpeed => "animation_speed",
+            Toggle::PieceSize => "piece_size",
+            Toggle::BoardGap => "board_gap",
+            Toggle::Notation => "notation",
+            Toggle::ClockStyle => "clock_style",
+            Toggle::ClockPlacement => "clock_placement",
+            Toggle::KeyProfile => "key_profile",
+            Toggle::Language => "language",
+        }
+    }
+
+    /// Current value, as shown in the panel.
+    fn state(self, settings: &Settings) -> &'static str {
+        let on = match self {
+            Toggle::ConfirmMoves => settings.confirm_moves,
+            Toggle::PieceLabels => settings.piece_labels,
+            Toggle::CameraCuts => settings.camera_cuts,
+            Toggle::AutoCamera => settings.auto_camera,
+            Toggle::ReducedMotion => settings.reduced_motion,
+            Toggle::MoveCounts => settings.move_counts,
+            Toggle::ShowThreats => settings.show_threats,
+            Toggle::FocusMode => settings.focus_mode,
+            Toggle::AudioCues => settings.audio_cues,
+            Toggle::SilentClock => settings.silent_clock,
+            Toggle::AnimationSpeed => {
+                return tr(settings.language, settings.animation_speed.label())
+            }
+            Toggle::PieceSize => return tr(settings.language, settings.piece_size.label()),
+            Toggle::BoardGap => return tr(settings.language, settings.board_gap.label()),
+            Toggle::Notation => return tr(settings.language, settings.notation.label()),
+            Toggle::ClockStyle => return tr(settings.language, settings.clock_style.label()),
+            Toggle::ClockPlacement => {
+                return tr(settings.language, settings.clock_placement.label())
+            }
+            Toggle::KeyProfile => return tr(settings.language, settings.key_profile.label()),
+            Toggle::Language => return settings.language.name(),
+        };
+        tr(settings.language, if on { "on" } else { "off" })
+    }
+
+    fn flip(self, settings: &mut Settings) {
+        match self {
+            Toggle::ConfirmMoves => settings.confirm_moves = !settings.confirm_moves,
+            Toggle::PieceLabels => settings.piece_labels = !settings.piece_labels,
+            Toggle::CameraCuts => settings.camera_cuts = !settings.camera_cuts,
+            Toggle::AutoCamera => settings.auto_camera = !settings.auto_camera,
+            Toggle::ReducedMotion => settings.reduced_motion = !settings.reduced_motion,
+            Toggle::MoveCounts => settings.move_counts = !settings.move_counts,
+            Toggle::ShowThreats => settings.show_threats = !settings.show_threats,
+            Toggle::FocusMode => settings.focus_mode = !settings.focus_mode,
+            Toggle::AudioCues => settings.audio_cues = !settings.audio_cues,
+            Toggle::SilentClock => settings.silent_clock = !settings.silent_clock,
+            Toggle::AnimationSpeed => settings.animation_speed = settings.animation_speed.next(),
+            Toggle::PieceSize => settings.piece_size = settings.piece_size.next(),
+            Toggle::BoardGap => settings.board_gap = settings.board_gap.next(),
+            Toggle::Notation => settings.notation = settings.notation.next(),
+            Toggle::ClockStyle => settings.clock_style = settings.clock_style.next(),
+            Toggle::ClockPlacement => settings.clock_placement = settings.clock_placement.next(),
+            Toggle::KeyProfile => settings.key_profile = settings.key_profile.next(),
+            Toggle::Language => settings.language = settings.language.next(),
+        }
+    }
+
+    fn text(self, settings: &Settings) -> String {
+        format!(
+            "{}: {}",
+            tr(settings.language, self.label()),
+            self.state(settings)
+        )
+    }
+}
+
+/// Button opening the settings panel
+struct SettingsButton;
+
+/// Root of the settings panel
+struct SettingsPanel;
+
+/// Button flipping a toggle
+struct ToggleButton(Toggle);
+
+/// Text showing the state of a toggle
+struct ToggleText(Toggle);
+
+/// Initialize settings button
+fn init_settings_ui(mut commands: Commands, assets: Res<UiAssets>) {
+    commands
+        .spawn_bundle(ButtonBundle {
+            style: Style {
+                position_type: PositionType::Absolute,
+                position: Rect {
+                    right: Val::Px(10.0),
+                    bottom: Val::Px(10.0),
+                    ..Default::default()
+                },
+                padding: Rect::all(Val::Px(8.0)),
+                ..Default::default()
+            },
+            material: assets.button.clone(),
+            ..Default::default()
+        })
+        .insert(SettingsButton)
+        .with_children(|parent| {
+            parent
+                .spawn_bundle(TextBundle {
+                    text: assets.text(tr(Language::default(), "settings"), 30.0),
+                    ..Default::default()
+                })
+                .insert(Localized("settings"));
+        });
+}
+
+/// Open or close the settings panel
+fn toggle_settings_panel(
+    mut commands: Commands,
+    assets: Res<UiAssets>,
+    settings: Res<Settings>,
+    keys: Res<Input<KeyCode>>,
+    bindings: Res<KeyBindings>,
+    button_query: Query<&Interaction, (Changed<Interaction>, With<SettingsButton>)>,
+    panel_query: Query<Entity, With<SettingsPanel>>,
+) {
+    let clicked = button_query.iter().any(|i| *i == Interaction::Clicked);
+    if !clicked && !keys.just_pressed(bindings.menu) {
+        return;
+    }
+
+    // Close the panel if already opened
+    if let Some(panel) = panel_query.iter().next() {
+        commands.entity(panel).despawn_recursive();
+        return;
+    }
+
+    commands
+        .spawn_bundle(NodeBundle {
+            style: Style {
+                position_type: PositionType::Absolute,
+                position: Rect {
+                    right: Val::Px(10.0),
+                    bottom: Val::Px(70.0),
+                    ..Default::default()
+                },
+                // Reversed as UI nodes are laid out from the bottom
+                flex_direction: FlexDirection::ColumnReverse,
+                padding: Rect::all(Val::Px(4.0)),
+                ..Default::default()
+            },
+            material: assets.panel.clone(),
+            ..Default::default()
+        })
+        .insert(SettingsPanel)
+        .with_children(|parent| {
+            for &toggle in Toggle::ALL {
+                parent
+                    .spawn_bundle(ButtonBundle {
+                        style: Style {
+                            margin: Rect::all(Val::Px(4.0)),
+                            padding: Rect::all(Val::Px(6.0)),
+                            ..Default::default()
+                        },
+                        material: assets.button.clone(),
+                        ..Default::default()
+                    })
+                    .insert(ToggleButton(toggle))
+                    .with_children(|parent| {
+                        parent
+                            .spawn_bundle(TextBundle {
+                                text: assets.text(&toggle.text(&settings), 24.0),
+                                ..Default::default()
+                            })
+                            .insert(ToggleText(toggle));
+                    });
+            }
+        });
+}
+
+/// Flip clicked toggles
+fn click_toggle(
+    mut settings: ResMut<Settings>,
+    button_query: Query<(&Interaction, &ToggleButton), Changed<Interaction>>,
+    mut text_query: Query<(&mut Text, &ToggleText)>,
+) {
+    for (interaction, button) in button_query.iter() {
+        if *interaction != Interaction::Clicked {
+            continue;
+        }
+
+        button.0.flip(&mut settings);
+
+        for (mut text, toggle_text) in text_query.iter_mut() {
+            text.sections[0].value = toggle_text.0.text(&settings);
+        }
+    }
+}
+
+/// Replace the key bindings when another key profile is chosen.
+/// Bindings inserted by the app are kept until the profile is changed.
+fn apply_key_profile(
+    settings: Res<Settings>,
+    mut bindings: ResMut<KeyBindings>,
+    mut applied: Local<KeyProfile>,
+) {
+    if settings.key_profile != *applied {
+        *applied = settings.key_profile;
+        *bindings = settings.key_profile.bindings();
+    }
+}
+
+/// Settings Plugin
+pub struct SettingsPlugin;
+
+impl Plugin for SettingsPlugin {
+    fn build(&self, app: &mut AppBuilder) {
+        app.init_resource::<Settings>()
+            .init_resource::<KeyBindings>()
+            .add_startup_system(init_settings_ui.system())
+            .add_system(toggle_settings_panel.system())
+            .add_system(click_toggle.system())
+            .add_system(apply_key_profile.system());
+    }
+}
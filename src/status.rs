@@ -0,0 +1,206 @@
+use bevy::prelude::*;
+
+use crate::clock::{ClockState, ClockStateEvent};
+use crate::error::ChessError;
+use crate::locale::*;
+use crate::settings::Settings;
+use crate::ui::UiAssets;
+
+use std::fs::OpenOptions;
+use std::io::Write;
+use std::path::PathBuf;
+use std::time::SystemTime;
+
+/// Time a toast stays on screen, in seconds.
+const TOAST_DURATION: f32 = 3.0;
+/// Part of the toast duration spent fading out.
+const TOAST_FADE: f32 = 0.3;
+
+/// Event showing a short message to the players, e.g. "Illegal move".
+pub struct StatusMessage(pub String);
+
+impl StatusMessage {
+    pub fn new(message: impl Into<String>) -> Self {
+        Self(message.into())
+    }
+}
+
+/// Where unexpected errors are written, insert it before the plugins to change it.
+pub struct ErrorLog {
+    /// File errors are appended to, or `None` to only print them in the console log
+    pub path: Option<PathBuf>,
+}
+
+impl Default for ErrorLog {
+    fn default() -> Self {
+        ErrorLog {
+            path: Some(PathBuf::from("bevy_chess_errors.log")),
+        }
+    }
+}
+
+/// Column stacking the toasts above the status bar
+struct ToastContainer;
+
+/// Message fading out once its timer runs out
+struct Toast {
+    timer: Timer,
+}
+
+/// Initialize the toast container
+fn init_toasts(mut commands: Commands, assets: Res<UiAssets>) {
+    commands
+        .spawn_bundle(NodeBundle {
+            style: Style {
+                position_type: PositionType::Absolute,
+                position: Rect {
+                    left: Val::Px(0.0),
+                    right: Val::Px(0.0),
+                    bottom: Val::Px(70.0),
+                    ..Default::default()
+                },
+                flex_direction: FlexDirection::Column,
+                align_items: AlignItems::Center,
+                ..Default::default()
+            },
+            material: assets.transparent.clone(),
+            ..Default::default()
+        })
+        .insert(ToastContainer);
+}
+
+/// Report flagged players
+fn clock_messages(
+    settings: Res<Settings>,
+    mut clock_state_events: EventReader<ClockStateEvent>,
+    mut status_messages: EventWriter<StatusMessage>,
+) {
+    for event in clock_state_events.iter() {
+        if event.state == ClockState::Flagged {
+            let color = color_name(settings.language, event.color);
+            status_messages.send(StatusMessage(tr_with(
+                settings.language,
+                "out_of_time",
+                color,
+            )));
+        }
+    }
+}
+
+/// Log unexpected errors and tell the players something went wrong
+fn error_messages(
+    settings: Res<Settings>,
+    log: Res<ErrorLog>,
+    mut errors: EventReader<ChessError>,
+    mut status_messages: EventWriter<StatusMessage>,
+) {
+    for err in errors.iter() {
+        error!("{}", err);
+        if let Some(path) = &log.path {
+            let seconds = SystemTime::now()
+                .duration_since(SystemTime::UNIX_EPOCH)
+                .map_or(0, |time| time.as_secs());
+            let written = OpenOptions::new()
+                .create(true)
+                .append(true)
+                .open(path)
+                .and_then(|mut file| writeln!(file, "{} {}", seconds, err));
+            if let Err(write_err) = written {
+                warn!("cannot write the error log {:?}: {}", path, write_err);
+            }
+        }
+        status_messages.send(StatusMessage(tr_with(
+            settings.language,
+            "unexpected_error",
+            &err.to_string(),
+        )));
+    }
+}
+
+/// Show status messages as toasts
+fn spawn_toasts(
+    mut commands: Commands,
+    assets: Res<UiAssets>,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+    mut status_messages: EventReader<StatusMessage>,
+    container_query: Query<Entity, With<ToastContainer>>,
+) {
+    let container = match container_query.iter().next() {
+        Some(container) => container,
+        None => return,
+    };
+
+    for message in status_messages.iter() {
+        info!("{}", message.0);
+        // Each toast fades its own background
+        let background = materials.add(Color::rgba(0.0, 0.0, 0.0, 0.6).into());
+        commands.entity(container).with_children(|parent| {
+            parent
+                .spawn_bundle(NodeBundle {
+                    style: Style {
+                        margin: Rect::all(Val::Px(4.0)),
+                        padding: Rect::all(Val::Px(8.0)),
+                        ..Default::default()
+                    },
+                    material: background,
+                    ..Default::default()
+                })
+                .insert(Toast {
+                    timer: Timer::from_seconds(TOAST_DURATION, false),
+                })
+                .with_children(|parent| {
+                    parent.spawn_bundle(TextBundle {
+                        text: assets.text(&message.0, 28.0),
+                        ..Default::default()
+                    });
+                });
+        });
+    }
+}
+
+/// Fade toasts out, and remove them once invisible
+fn fade_toasts(
+    mut commands: Commands,
+    time: Res<Time>,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+    mut toast_query: Query<(Entity, &mut Toast, &Handle<ColorMaterial>, &Children)>,
+    mut text_query: Query<&mut Text>,
+) {
+    for (entity, mut toast, material, children) in toast_query.iter_mut() {
+        toast.timer.tick(time.delta());
+        if toast.timer.finished() {
+            commands.entity(entity).despawn_recursive();
+            continue;
+        }
+
+        let left = toast.timer.percent_left();
+        if left > TOAST_FADE {
+            continue;
+        }
+        let alpha = left / TOAST_FADE;
+        if let Some(material) = materials.get_mut(material) {
+            material.color.set_a(0.6 * alpha);
+        }
+        for child in children.iter() {
+            if let Ok(mut text) = text_query.get_mut(*child) {
+                text.sections[0].style.color.set_a(alpha);
+            }
+        }
+    }
+}
+
+/// Status Plugin
+pub struct StatusPlugin;
+
+impl Plugin for StatusPlugin {
+    fn build(&self, app: &mut AppBuilder) {
+        app.add_event::<StatusMessage>()
+            .add_event::<ChessError>()
+            .init_resource::<ErrorLog>()
+            .add_startup_system(init_toasts.system())
+            .add_system(clock_messages.system().label("status_message"))
+            .add_system(error_messages.system().label("status_message"))
+            .add_system(spawn_toasts.system().after("status_message"))
+            .add_system(fade_toasts.system());
+    }
+}
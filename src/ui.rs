@@ -1,65 +1,1366 @@
+use crate::assets::*;
 use crate::board::*;
+use crate::clock::*;
+use crate::config::GameConfig;
+use crate::fen::to_fen;
+use crate::history::{AnnotateMoveEvent, MoveHistory, Nag};
+use crate::locale::*;
+use crate::pieces::{BoardState, PieceColor, PieceType};
+use crate::settings::{ClockPlacement, ClockStyle, NotationStyle, Settings};
+use crate::status::StatusMessage;
+use bevy::ecs::{bundle::Bundle, component::Component};
 use bevy::prelude::*;
+use bevy::render::camera::PerspectiveProjection;
+use bevy::window::WindowResized;
+
+use std::time::Duration;
+
+/// Font and materials shared by UI widgets.
+pub struct UiAssets {
+    pub font: Handle<Font>,
+    /// Font with the chess glyphs of figurine notation
+    pub figurine_font: Handle<Font>,
+    pub button: Handle<ColorMaterial>,
+    pub hovered: Handle<ColorMaterial>,
+    pub panel: Handle<ColorMaterial>,
+    pub progress: Handle<ColorMaterial>,
+    pub transparent: Handle<ColorMaterial>,
+}
+
+impl FromWorld for UiAssets {
+    fn from_world(world: &mut World) -> Self {
+        // Inserted by DefaultPlugins, which the game plugins must be added after
+        let asset_server = world
+            .get_resource::<AssetServer>()
+            .expect("DefaultPlugins must be added before the game plugins");
+        let font = asset_server.load("fonts/FiraSans-Bold.ttf");
+        let figurine_font = asset_server.load("fonts/DejaVuSans.ttf");
+        let mut materials = world
+            .get_resource_mut::<Assets<ColorMaterial>>()
+            .expect("DefaultPlugins must be added before the game plugins");
+        Self {
+            font,
+            figurine_font,
+            button: materials.add(Color::rgb(0.15, 0.15, 0.15).into()),
+            hovered: materials.add(Color::rgb(0.3, 0.3, 0.3).into()),
+            panel: materials.add(Color::rgba(0.0, 0.0, 0.0, 0.6).into()),
+            progress: materials.add(Color::rgb(0.1, 0.6, 0.1).into()),
+            transparent: materials.add(Color::NONE.into()),
+        }
+    }
+}
+
+impl UiAssets {
+    /// Text in the UI font.
+    pub fn text(&self, value: &str, font_size: f32) -> Text {
+        Text::with_section(
+            value.to_string(),
+            TextStyle {
+                font: self.font.clone(),
+                font_size,
+                color: Color::rgb(0.8, 0.8, 0.8),
+            },
+            TextAlignment::default(),
+        )
+    }
+
+    /// Button with a text label.
+    pub fn spawn_button(
+        &self,
+        parent: &mut ChildBuilder,
+        label: &str,
+        font_size: f32,
+        component: impl Component,
+    ) {
+        self.spawn_button_with(parent, label, font_size, component, ());
+    }
+
+    /// Button with a label translated again when the language changes.
+    pub fn spawn_localized_button(
+        &self,
+        parent: &mut ChildBuilder,
+        language: Language,
+        key: &'static str,
+        font_size: f32,
+        component: impl Component,
+    ) {
+        let label = tr(language, key);
+        self.spawn_button_with(parent, label, font_size, component, Localized(key));
+    }
+
+    fn spawn_button_with(
+        &self,
+        parent: &mut ChildBuilder,
+        label: &str,
+        font_size: f32,
+        component: impl Component,
+        text_bundle: impl Bundle,
+    ) {
+        parent
+            .spawn_bundle(ButtonBundle {
+                style: Style {
+                    margin: Rect::all(Val::Px(4.0)),
+                    padding: Rect::all(Val::Px(6.0)),
+                    ..Default::default()
+                },
+                material: self.button.clone(),
+                ..Default::default()
+            })
+            .insert(component)
+            .with_children(|parent| {
+                parent
+                    .spawn_bundle(TextBundle {
+                        text: self.text(label, font_size),
+                        ..Default::default()
+                    })
+                    .insert_bundle(text_bundle);
+            });
+    }
+}
 
 /// Text entity marker
 struct NextMoveText;
 
-/// Initialize UiCamera and text
-fn init_next_move_text(
+/// Clock text marker
+struct ClockText(PieceColor);
+
+/// Pieces captured by a player
+struct CapturedText(PieceColor);
+
+/// Move history text
+struct HistoryText;
+
+/// Column of the move history panel, resized by dragging its edge
+struct HistoryPanel;
+
+/// Content of the move history panel, hidden when it is collapsed
+struct HistoryBody;
+
+/// Edge of the move history panel
+struct HistoryResizeHandle;
+
+/// Button collapsing the move history panel
+struct CollapseHistoryButton;
+
+/// Text of the collapse button
+struct CollapseHistoryText;
+
+/// Width of the edge dragged to resize the move history panel, in pixels.
+const RESIZE_HANDLE_WIDTH: f32 = 6.0;
+/// Narrowest width of the move history panel, in pixels.
+const HISTORY_MIN_WIDTH: f32 = 200.0;
+
+/// Name of the hovered square
+struct HoveredSquareText;
+
+/// Button copying the game to the clipboard
+#[derive(Clone, Copy)]
+enum CopyButton {
+    /// Move list in standard algebraic notation
+    Moves,
+    /// Current position
+    Fen,
+}
+
+/// Button setting the annotation glyph of the last move, or clearing it when already set
+struct NagButton(Nag);
+
+/// Bar at the bottom of the window
+pub(crate) struct StatusBar;
+
+/// Clock border, pulsing while its player is in time scramble
+struct ClockBorder {
+    color: PieceColor,
+    low_time: bool,
+}
+
+/// Where a clock is shown, only shown while its placement is chosen
+struct ClockSlot(ClockPlacement);
+
+/// Face of an analog clock
+struct ClockDial;
+
+/// Node turning the hand of an analog clock
+struct ClockHand(PieceColor);
+
+/// Bar under a clock filling up with the time spent on the current move,
+/// shown to the player to move when moves are time limited
+struct TurnBar(PieceColor);
+
+/// Filled part of a turn bar
+struct TurnBarFill;
+
+/// Width and height of analog clocks, in pixels.
+const DIAL_SIZE: f32 = 48.0;
+/// Horizontal offset roughly centering a clock beside the board on its anchor, in pixels.
+const BOARD_CLOCK_OFFSET: f32 = -45.0;
+
+const CLOCK_TEXT_COLOR: Color = Color::rgb(0.8, 0.8, 0.8);
+const CLOCK_BORDER_COLOR: Color = Color::rgb(0.8, 0.1, 0.1);
+
+/// Show the share of the time allowed for the current move already spent
+fn turn_bar_update(
+    config: Res<GameConfig>,
+    clock: Res<ChessClock>,
+    turn: Res<PlayerTurn>,
+    mut clock_tick_events: EventReader<ClockTickEvent>,
+    mut bar_query: Query<(&TurnBar, &Children, &mut Style)>,
+    mut fill_query: Query<&mut Style, (With<TurnBarFill>, Without<TurnBar>)>,
+) {
+    let ticked = clock_tick_events.iter().last().is_some();
+    if !ticked && !turn.is_changed() && !config.is_changed() {
+        return;
+    }
+
+    let allowance = config.move_allowance();
+    for (bar, children, mut style) in bar_query.iter_mut() {
+        let display = match allowance {
+            Some(_) if bar.0 == turn.0 => Display::Flex,
+            _ => Display::None,
+        };
+        // Avoid relayouting the clocks every frame
+        if style.display != display {
+            style.display = display;
+        }
+
+        let allowance = match allowance {
+            Some(allowance) if display == Display::Flex => allowance,
+            _ => continue,
+        };
+        let fraction = (clock.spent.as_secs_f32() / allowance.as_secs_f32()).min(1.0);
+        for &child in children.iter() {
+            if let Ok(mut style) = fill_query.get_mut(child) {
+                style.size.width = Val::Percent(fraction * 100.0);
+            }
+        }
+    }
+}
+
+/// Update text with turn
+fn next_move_text_update(
+    turn: Res<PlayerTurn>,
+    settings: Res<Settings>,
+    mut query: Query<&mut Text, With<NextMoveText>>,
+) {
+    if !turn.is_changed() && !settings.is_changed() {
+        return;
+    }
+
+    let color = color_name(settings.language, turn.0);
+    let value = tr_with(settings.language, "next_move", color);
+    for mut text in query.iter_mut() {
+        text.sections[0].value = value.clone();
+    }
+}
+
+/// Format remaining time, showing tenths of seconds in time scramble
+fn format_clock(remaining: Duration) -> String {
+    let secs = remaining.as_secs();
+    if remaining < LOW_TIME {
+        format!("{}.{}", secs, remaining.subsec_millis() / 100)
+    } else if secs >= 60 * 60 {
+        format!("{}:{:02}:{:02}", secs / 3600, secs / 60 % 60, secs % 60)
+    } else {
+        format!("{}:{:02}", secs / 60, secs % 60)
+    }
+}
+
+/// Initialize UiCamera and the HUD layout: clocks and captured pieces on the left,
+/// move history on the right, and a status bar at the bottom
+fn init_layout(
     mut commands: Commands,
-    asset_server: ResMut<AssetServer>,
+    assets: Res<UiAssets>,
     mut color_material: ResMut<Assets<ColorMaterial>>,
+    clock: Res<ChessClock>,
 ) {
-    let font = asset_server.load("fonts/FiraSans-Bold.ttf");
-    let material = color_material.add(Color::NONE.into());
-
     commands.spawn_bundle(UiCameraBundle::default());
     commands
         .spawn_bundle(NodeBundle {
             style: Style {
+                size: Size::new(Val::Percent(100.0), Val::Percent(100.0)),
                 position_type: PositionType::Absolute,
-                position: Rect {
-                    left: Val::Px(10.0),
-                    top: Val::Px(10.0),
+                // Reversed as UI nodes are laid out from the bottom
+                flex_direction: FlexDirection::ColumnReverse,
+                justify_content: JustifyContent::SpaceBetween,
+                ..Default::default()
+            },
+            material: assets.transparent.clone(),
+            ..Default::default()
+        })
+        .with_children(|parent| {
+            parent
+                .spawn_bundle(NodeBundle {
+                    style: Style {
+                        justify_content: JustifyContent::SpaceBetween,
+                        align_items: AlignItems::FlexStart,
+                        ..Default::default()
+                    },
+                    material: assets.transparent.clone(),
                     ..Default::default()
-                },
+                })
+                .with_children(|parent| {
+                    spawn_left_column(parent, &assets, &mut color_material, &clock);
+                    spawn_right_column(parent, &assets);
+                });
+            spawn_status_bar(parent, &assets);
+        });
+    spawn_board_clocks(&mut commands, &assets, &mut color_material, &clock);
+}
+
+/// Column holding the clocks and the pieces captured by each player
+fn spawn_left_column(
+    parent: &mut ChildBuilder,
+    assets: &UiAssets,
+    color_material: &mut Assets<ColorMaterial>,
+    clock: &ChessClock,
+) {
+    parent
+        .spawn_bundle(NodeBundle {
+            style: Style {
+                flex_direction: FlexDirection::ColumnReverse,
+                align_items: AlignItems::FlexStart,
+                padding: Rect::all(Val::Px(10.0)),
                 ..Default::default()
             },
-            material,
+            material: assets.transparent.clone(),
             ..Default::default()
         })
         .with_children(|parent| {
+            for &color in [PieceColor::Black, PieceColor::White].iter() {
+                let style = Style {
+                    margin: Rect {
+                        top: Val::Px(6.0),
+                        ..Default::default()
+                    },
+                    ..Default::default()
+                };
+                spawn_clock(
+                    parent,
+                    assets,
+                    color_material,
+                    clock,
+                    color,
+                    ClockPlacement::Corner,
+                    style,
+                );
+                parent
+                    .spawn_bundle(TextBundle {
+                        text: assets.text("", 24.0),
+                        ..Default::default()
+                    })
+                    .insert(CapturedText(color));
+            }
             parent
                 .spawn_bundle(TextBundle {
-                    text: Text::with_section(
-                        "Next move: White".to_string(),
-                        TextStyle {
-                            font,
-                            font_size: 40.0,
-                            color: Color::rgb(0.8, 0.8, 0.8),
+                    style: Style {
+                        margin: Rect {
+                            top: Val::Px(12.0),
+                            ..Default::default()
+                        },
+                        ..Default::default()
+                    },
+                    text: assets.text("", 40.0),
+                    ..Default::default()
+                })
+                .insert(HoveredSquareText);
+        });
+}
+
+/// Clock of a player, with both its digital and analog faces
+fn spawn_clock(
+    parent: &mut ChildBuilder,
+    assets: &UiAssets,
+    color_material: &mut Assets<ColorMaterial>,
+    clock: &ChessClock,
+    color: PieceColor,
+    placement: ClockPlacement,
+    style: Style,
+) {
+    let mut border_color = CLOCK_BORDER_COLOR;
+    border_color.set_a(0.0);
+    let border = color_material.add(border_color.into());
+    let background = color_material.add(Color::rgb(0.1, 0.1, 0.1).into());
+    let face = color_material.add(Color::rgb(0.85, 0.85, 0.8).into());
+    let hand = color_material.add(Color::rgb(0.1, 0.1, 0.1).into());
+    let flag = color_material.add(CLOCK_BORDER_COLOR.into());
+
+    parent
+        .spawn_bundle(NodeBundle {
+            style: Style {
+                padding: Rect::all(Val::Px(4.0)),
+                // Reversed as UI nodes are laid out from the bottom
+                flex_direction: FlexDirection::ColumnReverse,
+                ..style
+            },
+            material: border,
+            ..Default::default()
+        })
+        .insert(ClockBorder {
+            color,
+            low_time: false,
+        })
+        .insert(ClockSlot(placement))
+        .with_children(|parent| {
+            parent
+                .spawn_bundle(NodeBundle {
+                    style: Style {
+                        padding: Rect::all(Val::Px(4.0)),
+                        ..Default::default()
+                    },
+                    material: background,
+                    ..Default::default()
+                })
+                .with_children(|parent| {
+                    parent
+                        .spawn_bundle(TextBundle {
+                            text: assets.text(&format_clock(clock.remaining(color)), 40.0),
+                            ..Default::default()
+                        })
+                        .insert(ClockText(color))
+                        .insert(ClockSlot(placement));
+                    parent
+                        .spawn_bundle(NodeBundle {
+                            style: Style {
+                                size: Size::new(Val::Px(DIAL_SIZE), Val::Px(DIAL_SIZE)),
+                                display: Display::None,
+                                ..Default::default()
+                            },
+                            material: face,
+                            ..Default::default()
+                        })
+                        .insert(ClockDial)
+                        .insert(ClockSlot(placement))
+                        .with_children(|parent| {
+                            // Flag at twelve o'clock, reached by the hand once the time is up
+                            parent.spawn_bundle(NodeBundle {
+                                style: Style {
+                                    position_type: PositionType::Absolute,
+                                    position: Rect {
+                                        left: Val::Px(DIAL_SIZE / 2.0 - 2.0),
+                                        top: Val::Px(0.0),
+                                        ..Default::default()
+                                    },
+                                    size: Size::new(Val::Px(4.0), Val::Px(6.0)),
+                                    ..Default::default()
+                                },
+                                material: flag,
+                                ..Default::default()
+                            });
+                            // The hand turns with this node, covering the whole dial
+                            parent
+                                .spawn_bundle(NodeBundle {
+                                    style: Style {
+                                        position_type: PositionType::Absolute,
+                                        size: Size::new(Val::Px(DIAL_SIZE), Val::Px(DIAL_SIZE)),
+                                        ..Default::default()
+                                    },
+                                    material: assets.transparent.clone(),
+                                    ..Default::default()
+                                })
+                                .insert(ClockHand(color))
+                                .with_children(|parent| {
+                                    parent.spawn_bundle(NodeBundle {
+                                        style: Style {
+                                            position_type: PositionType::Absolute,
+                                            position: Rect {
+                                                left: Val::Px(DIAL_SIZE / 2.0 - 1.5),
+                                                bottom: Val::Px(DIAL_SIZE / 2.0),
+                                                ..Default::default()
+                                            },
+                                            size: Size::new(
+                                                Val::Px(3.0),
+                                                Val::Px(DIAL_SIZE / 2.0 - 6.0),
+                                            ),
+                                            ..Default::default()
+                                        },
+                                        material: hand,
+                                        ..Default::default()
+                                    });
+                                });
+                        });
+                });
+            parent
+                .spawn_bundle(NodeBundle {
+                    style: Style {
+                        size: Size::new(Val::Auto, Val::Px(4.0)),
+                        margin: Rect {
+                            top: Val::Px(4.0),
+                            ..Default::default()
                         },
-                        TextAlignment::default(),
-                    ),
+                        display: Display::None,
+                        ..Default::default()
+                    },
+                    material: assets.button.clone(),
+                    ..Default::default()
+                })
+                .insert(TurnBar(color))
+                .with_children(|parent| {
+                    parent
+                        .spawn_bundle(NodeBundle {
+                            style: Style {
+                                size: Size::new(Val::Percent(0.0), Val::Percent(100.0)),
+                                ..Default::default()
+                            },
+                            material: assets.progress.clone(),
+                            ..Default::default()
+                        })
+                        .insert(TurnBarFill);
+                });
+        });
+}
+
+/// Clocks placed beside the board, hidden until chosen in the settings
+fn spawn_board_clocks(
+    commands: &mut Commands,
+    assets: &UiAssets,
+    color_material: &mut Assets<ColorMaterial>,
+    clock: &ChessClock,
+) {
+    commands
+        .spawn_bundle(NodeBundle {
+            style: Style {
+                size: Size::new(Val::Percent(100.0), Val::Percent(100.0)),
+                position_type: PositionType::Absolute,
+                ..Default::default()
+            },
+            material: assets.transparent.clone(),
+            ..Default::default()
+        })
+        .with_children(|parent| {
+            for &color in [PieceColor::White, PieceColor::Black].iter() {
+                let style = Style {
+                    position_type: PositionType::Absolute,
+                    display: Display::None,
+                    ..Default::default()
+                };
+                spawn_clock(
+                    parent,
+                    assets,
+                    color_material,
+                    clock,
+                    color,
+                    ClockPlacement::Board,
+                    style,
+                );
+            }
+        });
+}
+
+/// Column holding the move history
+fn spawn_right_column(parent: &mut ChildBuilder, assets: &UiAssets) {
+    parent
+        .spawn_bundle(NodeBundle {
+            material: assets.panel.clone(),
+            ..Default::default()
+        })
+        .with_children(|parent| {
+            // Edge dragged to resize the panel
+            parent
+                .spawn_bundle(ButtonBundle {
+                    style: Style {
+                        size: Size::new(Val::Px(RESIZE_HANDLE_WIDTH), Val::Auto),
+                        ..Default::default()
+                    },
+                    material: assets.button.clone(),
+                    ..Default::default()
+                })
+                .insert(HistoryResizeHandle);
+            parent
+                .spawn_bundle(NodeBundle {
+                    style: Style {
+                        flex_direction: FlexDirection::ColumnReverse,
+                        align_items: AlignItems::FlexStart,
+                        min_size: Size::new(Val::Px(HISTORY_MIN_WIDTH), Val::Auto),
+                        padding: Rect::all(Val::Px(10.0)),
+                        ..Default::default()
+                    },
+                    material: assets.transparent.clone(),
+                    ..Default::default()
+                })
+                .insert(HistoryPanel)
+                .with_children(|parent| {
+                    parent
+                        .spawn_bundle(NodeBundle {
+                            style: Style {
+                                align_items: AlignItems::Center,
+                                ..Default::default()
+                            },
+                            material: assets.transparent.clone(),
+                            ..Default::default()
+                        })
+                        .with_children(|parent| {
+                            parent
+                                .spawn_bundle(TextBundle {
+                                    text: assets.text(tr(Language::default(), "moves"), 30.0),
+                                    ..Default::default()
+                                })
+                                .insert(Localized("moves"));
+                            assets.spawn_button_with(
+                                parent,
+                                "-",
+                                24.0,
+                                CollapseHistoryButton,
+                                CollapseHistoryText,
+                            );
+                        });
+                    parent
+                        .spawn_bundle(NodeBundle {
+                            style: Style {
+                                flex_direction: FlexDirection::ColumnReverse,
+                                align_items: AlignItems::FlexStart,
+                                ..Default::default()
+                            },
+                            material: assets.transparent.clone(),
+                            ..Default::default()
+                        })
+                        .insert(HistoryBody)
+                        .with_children(|parent| {
+                            spawn_history_body(parent, assets);
+                        });
+                });
+        });
+}
+
+/// Move history, its annotation and copy buttons, hidden when the panel is collapsed
+fn spawn_history_body(parent: &mut ChildBuilder, assets: &UiAssets) {
+    parent
+        .spawn_bundle(TextBundle {
+            text: assets.text("", 22.0),
+            ..Default::default()
+        })
+        .insert(HistoryText);
+    parent
+        .spawn_bundle(NodeBundle {
+            material: assets.transparent.clone(),
+            ..Default::default()
+        })
+        .with_children(|parent| {
+            let nags = [
+                Nag::Brilliant,
+                Nag::Good,
+                Nag::Interesting,
+                Nag::Dubious,
+                Nag::Mistake,
+                Nag::Blunder,
+            ];
+            for &nag in nags.iter() {
+                assets.spawn_button(parent, &nag.to_string(), 20.0, NagButton(nag));
+            }
+        });
+    parent
+        .spawn_bundle(NodeBundle {
+            material: assets.transparent.clone(),
+            ..Default::default()
+        })
+        .with_children(|parent| {
+            let language = Language::default();
+            assets.spawn_localized_button(parent, language, "copy_moves", 20.0, CopyButton::Moves);
+            assets.spawn_localized_button(parent, language, "copy_fen", 20.0, CopyButton::Fen);
+        });
+}
+
+/// Bar at the bottom of the window showing the game status
+fn spawn_status_bar(parent: &mut ChildBuilder, assets: &UiAssets) {
+    parent
+        .spawn_bundle(NodeBundle {
+            style: Style {
+                size: Size::new(Val::Percent(100.0), Val::Px(60.0)),
+                justify_content: JustifyContent::Center,
+                align_items: AlignItems::Center,
+                ..Default::default()
+            },
+            material: assets.transparent.clone(),
+            ..Default::default()
+        })
+        .insert(StatusBar)
+        .with_children(|parent| {
+            parent
+                .spawn_bundle(TextBundle {
+                    text: assets.text("", 40.0),
                     ..Default::default()
                 })
                 .insert(NextMoveText);
         });
 }
 
-/// Update text with turn
-fn next_move_text_update(turn: ResMut<PlayerTurn>, mut query: Query<(&mut Text, &NextMoveText)>) {
-    for (mut text, _) in query.iter_mut() {
-        if turn.is_changed() {
-            text.sections[0].value = format!("Next move: {}", *turn);
+/// Show the name of the hovered square, e.g. "e4"
+fn hovered_square_text_update(
+    hovered_square: Res<HoveredSquare>,
+    squares_query: Query<&Square>,
+    mut query: Query<&mut Text, With<HoveredSquareText>>,
+) {
+    if !hovered_square.is_changed() {
+        return;
+    }
+
+    let name = hovered_square
+        .entity
+        .and_then(|entity| squares_query.get(entity).ok())
+        .map_or(String::new(), |square| square_name((square.x, square.y)));
+    for mut text in query.iter_mut() {
+        text.sections[0].value = name.clone();
+    }
+}
+
+/// Number of full moves shown in the history column
+const HISTORY_LENGTH: usize = 20;
+
+/// List the last moves in the history column, one full move per line
+fn history_text_update(
+    history: Res<MoveHistory>,
+    settings: Res<Settings>,
+    assets: Res<UiAssets>,
+    mut query: Query<&mut Text, With<HistoryText>>,
+) {
+    if !history.is_changed() && !settings.is_changed() {
+        return;
+    }
+
+    let mut lines = Vec::new();
+    for (i, record) in history.moves.iter().enumerate() {
+        // Start a new line on White moves, or when the history starts with Black
+        if record.piece.color == PieceColor::White || i == 0 {
+            let number = history.move_number(i);
+            let prefix = if record.piece.color == PieceColor::White {
+                format!("{}.", number)
+            } else {
+                format!("{}. ...", number)
+            };
+            lines.push(prefix);
+        }
+        if let Some(line) = lines.last_mut() {
+            line.push(' ');
+            // Writing to a String cannot fail
+            let _ = record.write_styled(line, settings.notation, |piece_type| {
+                piece_letter(settings.language, piece_type)
+            });
         }
     }
+    let first = lines.len().saturating_sub(HISTORY_LENGTH);
+
+    // The UI font has no chess glyphs
+    let font = match settings.notation {
+        NotationStyle::Figurine => &assets.figurine_font,
+        _ => &assets.font,
+    };
+    for mut text in query.iter_mut() {
+        text.sections[0].value = lines[first..].join("\n");
+        text.sections[0].style.font = font.clone();
+    }
+}
+
+/// Collapse or expand the move history panel
+fn collapse_history_click(
+    mut settings: ResMut<Settings>,
+    query: Query<&Interaction, (Changed<Interaction>, With<CollapseHistoryButton>)>,
+) {
+    if query
+        .iter()
+        .any(|interaction| *interaction == Interaction::Clicked)
+    {
+        settings.history_collapsed = !settings.history_collapsed;
+    }
+}
+
+/// Resize the move history panel while its edge is dragged
+fn resize_history(
+    windows: Res<Windows>,
+    mut settings: ResMut<Settings>,
+    query: Query<&Interaction, With<HistoryResizeHandle>>,
+) {
+    // Clicked lasts until the mouse button is released, even off the handle
+    if !query
+        .iter()
+        .any(|interaction| *interaction == Interaction::Clicked)
+    {
+        return;
+    }
+    let window = match windows.get_primary() {
+        Some(window) => window,
+        None => return,
+    };
+    let cursor = match window.cursor_position() {
+        Some(cursor) => cursor,
+        None => return,
+    };
+
+    // The panel sits against the right edge of the window
+    let width = (window.width() - cursor.x - RESIZE_HANDLE_WIDTH)
+        .min(window.width() / 2.0)
+        .max(HISTORY_MIN_WIDTH)
+        .round();
+    if settings.history_width != Some(width) {
+        settings.history_width = Some(width);
+    }
+}
+
+/// Apply the size and collapsed state of the move history panel from the settings
+fn history_panel_update(
+    settings: Res<Settings>,
+    mut panels: Query<&mut Style, (With<HistoryPanel>, Without<HistoryBody>)>,
+    mut bodies: Query<&mut Style, (With<HistoryBody>, Without<HistoryPanel>)>,
+    mut texts: Query<&mut Text, With<CollapseHistoryText>>,
+) {
+    if !settings.is_changed() {
+        return;
+    }
+
+    let width = match settings.history_width {
+        Some(width) if !settings.history_collapsed => Val::Px(width),
+        _ => Val::Auto,
+    };
+    for mut style in panels.iter_mut() {
+        if style.size.width != width {
+            style.size.width = width;
+        }
+    }
+    let display = if settings.history_collapsed {
+        Display::None
+    } else {
+        Display::Flex
+    };
+    for mut style in bodies.iter_mut() {
+        if style.display != display {
+            style.display = display;
+        }
+    }
+    for mut text in texts.iter_mut() {
+        text.sections[0].value = String::from(if settings.history_collapsed { "+" } else { "-" });
+    }
+}
+
+/// List the pieces captured by each player under their clock
+fn captured_text_update(
+    history: Res<MoveHistory>,
+    settings: Res<Settings>,
+    mut query: Query<(&mut Text, &CapturedText)>,
+) {
+    if !history.is_changed() && !settings.is_changed() {
+        return;
+    }
+
+    for (mut text, captured_text) in query.iter_mut() {
+        let captured: Vec<&str> = history
+            .moves
+            .iter()
+            .filter(|record| record.piece.color == captured_text.0)
+            .filter_map(|record| record.captured)
+            .map(|piece| match piece.piece_type {
+                PieceType::Pawn => "P",
+                piece_type => piece_letter(settings.language, piece_type),
+            })
+            .collect();
+        text.sections[0].value = captured.join(" ");
+    }
+}
+
+/// Update clock texts with remaining time
+fn clock_text_update(
+    clock: Res<ChessClock>,
+    mut clock_tick_events: EventReader<ClockTickEvent>,
+    mut query: Query<(&mut Text, &ClockText)>,
+) {
+    if clock_tick_events.iter().last().is_none() {
+        return;
+    }
+    for (mut text, clock_text) in query.iter_mut() {
+        text.sections[0].value = format_clock(clock.remaining(clock_text.0));
+    }
+}
+
+/// Turn the hands of analog clocks. Like on a mechanical chess clock, the hand
+/// reaches twelve o'clock when the time is up, so it shows minutes left
+fn clock_hand_update(
+    clock: Res<ChessClock>,
+    mut clock_tick_events: EventReader<ClockTickEvent>,
+    mut query: Query<(&mut Transform, &ClockHand)>,
+) {
+    if clock_tick_events.iter().last().is_none() {
+        return;
+    }
+    for (mut transform, hand) in query.iter_mut() {
+        let hours = clock.remaining(hand.0).as_secs_f32() / 3600.0;
+        transform.rotation = Quat::from_rotation_z(hours * std::f32::consts::TAU);
+    }
+}
+
+/// Show the clocks of the chosen placement and style, keeping those beside the board
+/// next to the side of their player
+fn place_clocks(
+    windows: Res<Windows>,
+    settings: Res<Settings>,
+    mut window_resized_events: EventReader<WindowResized>,
+    camera_query: Query<(&Camera, &GlobalTransform), With<PerspectiveProjection>>,
+    moved_camera_query: Query<(), (With<PerspectiveProjection>, Changed<GlobalTransform>)>,
+    mut borders: Query<(&ClockBorder, &ClockSlot, &mut Style), Without<ClockText>>,
+    mut texts: Query<(&ClockSlot, &mut Style, &mut Visible), With<ClockText>>,
+    mut dials: Query<
+        (&ClockSlot, &mut Style),
+        (With<ClockDial>, Without<ClockText>, Without<ClockBorder>),
+    >,
+) {
+    // Only the settings, or the view for clocks beside the board, move the clocks
+    let resized = window_resized_events.iter().last().is_some();
+    if !settings.is_changed() && !resized && moved_camera_query.iter().next().is_none() {
+        return;
+    }
+
+    let display = |slot: &ClockSlot, shown: bool| {
+        if shown && slot.0 == settings.clock_placement {
+            Display::Flex
+        } else {
+            Display::None
+        }
+    };
+    let camera = camera_query.iter().next();
+
+    for (border, slot, mut style) in borders.iter_mut() {
+        let display = display(slot, true);
+        // Avoid relayouting the clocks every frame
+        if style.display != display {
+            style.display = display;
+        }
+        if slot.0 != ClockPlacement::Board || display == Display::None {
+            continue;
+        }
+
+        // Both clocks stand on the same side of the board, each in front of its player
+        let anchor = match border.color {
+            PieceColor::White => Vec3::new(1.5, 0.0, 8.5),
+            PieceColor::Black => Vec3::new(5.5, 0.0, 8.5),
+        };
+        let position = camera.and_then(|(camera, camera_transform)| {
+            camera.world_to_screen(&windows, camera_transform, anchor)
+        });
+        if let Some(position) = position {
+            let left = Val::Px((position.x + BOARD_CLOCK_OFFSET).round());
+            let bottom = Val::Px(position.y.round());
+            if style.position.left != left || style.position.bottom != bottom {
+                style.position.left = left;
+                style.position.bottom = bottom;
+            }
+        }
+    }
+
+    let digital = settings.clock_style == ClockStyle::Digital;
+    for (slot, mut style, mut visible) in texts.iter_mut() {
+        let display = display(slot, digital);
+        if style.display != display {
+            style.display = display;
+        }
+        // Hidden texts are still drawn, only their layout collapses
+        visible.is_visible = display == Display::Flex;
+    }
+    for (slot, mut style) in dials.iter_mut() {
+        let display = display(slot, !digital);
+        if style.display != display {
+            style.display = display;
+        }
+    }
+}
+
+/// Turn clock red and start pulsing its border in time scramble
+fn clock_state_effects(
+    mut clock_state_events: EventReader<ClockStateEvent>,
+    mut texts: Query<(&mut Text, &ClockText)>,
+    mut borders: Query<&mut ClockBorder>,
+) {
+    for event in clock_state_events.iter() {
+        let low_time = event.state != ClockState::Normal;
+        for (mut text, clock_text) in texts.iter_mut() {
+            if clock_text.0 == event.color {
+                text.sections[0].style.color = if low_time {
+                    Color::RED
+                } else {
+                    CLOCK_TEXT_COLOR
+                };
+            }
+        }
+        for mut border in borders.iter_mut() {
+            if border.color == event.color {
+                border.low_time = low_time;
+            }
+        }
+    }
+}
+
+/// Pulse the border of clocks in time scramble
+fn pulse_clock_border(
+    time: Res<Time>,
+    settings: Res<Settings>,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+    query: Query<(&ClockBorder, &Handle<ColorMaterial>)>,
+    changed_query: Query<(), Changed<ClockBorder>>,
+) {
+    // Only pulsing borders, or ones just out of time scramble, need a new color
+    if changed_query.iter().next().is_none() && !query.iter().any(|(border, _)| border.low_time) {
+        return;
+    }
+
+    let pulse = if settings.reduced_motion {
+        1.0
+    } else {
+        (time.seconds_since_startup() * std::f64::consts::TAU).sin() as f32 * 0.5 + 0.5
+    };
+    for (border, handle) in query.iter() {
+        let alpha = if border.low_time { pulse } else { 0.0 };
+        // Avoid touching idle materials so they are not re-uploaded every frame
+        if materials.get(handle).map_or(true, |m| m.color.a() == alpha) {
+            continue;
+        }
+        if let Some(material) = materials.get_mut(handle) {
+            material.color.set_a(alpha);
+        }
+    }
+}
+
+/// Confirm button marker
+struct ConfirmButton;
+
+/// Root of the confirm button
+struct ConfirmButtonRoot;
+
+/// Show a confirm button while a move is pending
+fn confirm_button_update(
+    mut commands: Commands,
+    assets: Res<UiAssets>,
+    settings: Res<Settings>,
+    pending_move: Res<PendingMove>,
+    query: Query<Entity, With<ConfirmButtonRoot>>,
+    status_bar_query: Query<Entity, With<StatusBar>>,
+) {
+    if !pending_move.is_changed() {
+        return;
+    }
+
+    for entity in query.iter() {
+        commands.entity(entity).despawn_recursive();
+    }
+
+    if pending_move.0.is_some() {
+        for status_bar in status_bar_query.iter() {
+            commands.entity(status_bar).with_children(|parent| {
+                parent
+                    .spawn_bundle(NodeBundle {
+                        style: Style {
+                            margin: Rect {
+                                left: Val::Px(20.0),
+                                ..Default::default()
+                            },
+                            ..Default::default()
+                        },
+                        material: assets.panel.clone(),
+                        ..Default::default()
+                    })
+                    .insert(ConfirmButtonRoot)
+                    .with_children(|parent| {
+                        let label = tr(settings.language, "confirm_move");
+                        assets.spawn_button(parent, label, 30.0, ConfirmButton);
+                    });
+            });
+        }
+    }
+}
+
+/// Play the pending move when the confirm button is clicked
+fn confirm_button_click(
+    pending_move: Res<PendingMove>,
+    query: Query<&Interaction, (Changed<Interaction>, With<ConfirmButton>)>,
+    mut move_request_events: EventWriter<MoveRequestEvent>,
+) {
+    if query.iter().any(|i| *i == Interaction::Clicked) {
+        if let Some((piece, to)) = pending_move.0 {
+            move_request_events.send(MoveRequestEvent { piece, to });
+        }
+    }
+}
+
+/// Annotate the last move with the clicked glyph, keeping its comment
+fn nag_button_click(
+    history: Res<MoveHistory>,
+    query: Query<(&Interaction, &NagButton), Changed<Interaction>>,
+    mut annotate_move_events: EventWriter<AnnotateMoveEvent>,
+) {
+    let record = match history.moves.last() {
+        Some(record) => record,
+        None => return,
+    };
+    for (interaction, button) in query.iter() {
+        if *interaction != Interaction::Clicked {
+            continue;
+        }
+
+        let nag = if record.nag == Some(button.0) {
+            None
+        } else {
+            Some(button.0)
+        };
+        annotate_move_events.send(AnnotateMoveEvent {
+            index: history.moves.len() - 1,
+            nag,
+            comment: record.comment.clone(),
+        });
+    }
+}
+
+/// Copy the move list or the position to the system clipboard
+fn copy_button_click(
+    history: Res<MoveHistory>,
+    turn: Res<PlayerTurn>,
+    settings: Res<Settings>,
+    board: Res<BoardState>,
+    query: Query<(&Interaction, &CopyButton), Changed<Interaction>>,
+    mut status_messages: EventWriter<StatusMessage>,
+) {
+    for (interaction, button) in query.iter() {
+        if *interaction != Interaction::Clicked {
+            continue;
+        }
+
+        let text = match button {
+            CopyButton::Moves => history.movetext(),
+            CopyButton::Fen => to_fen(&board.pieces, turn.0, history.fullmove()),
+        };
+        let copied = arboard::Clipboard::new().and_then(|mut clipboard| clipboard.set_text(text));
+        let key = match copied {
+            Ok(()) => "copied",
+            Err(error) => {
+                warn!("Cannot copy to the clipboard: {}", error);
+                "copy_failed"
+            }
+        };
+        status_messages.send(StatusMessage::new(tr(settings.language, key)));
+    }
+}
+
+/// Highlight hovered buttons
+fn button_hover(
+    assets: Res<UiAssets>,
+    mut query: Query<
+        (&Interaction, &mut Handle<ColorMaterial>),
+        (Changed<Interaction>, With<Button>),
+    >,
+) {
+    for (interaction, mut material) in query.iter_mut() {
+        *material = match interaction {
+            Interaction::Hovered | Interaction::Clicked => assets.hovered.clone(),
+            Interaction::None => assets.button.clone(),
+        };
+    }
+}
+
+/// Delay before the result banner slides in, letting the camera swing first, in seconds.
+const BANNER_DELAY: f32 = 1.0;
+/// Duration of the banner slide, in seconds.
+const BANNER_SLIDE: f32 = 0.5;
+
+/// Banner announcing the winner, sliding in from the top of the window
+struct ResultBanner {
+    timer: Timer,
+}
+
+/// Show the winner when the game is over
+fn show_result_banner(
+    mut commands: Commands,
+    assets: Res<UiAssets>,
+    settings: Res<Settings>,
+    mut game_over_events: EventReader<GameOverEvent>,
+) {
+    let event = match game_over_events.iter().last() {
+        Some(event) => event,
+        None => return,
+    };
+
+    let winner = color_name(settings.language, event.winner);
+    commands
+        .spawn_bundle(NodeBundle {
+            style: Style {
+                position_type: PositionType::Absolute,
+                position: Rect {
+                    left: Val::Px(0.0),
+                    right: Val::Px(0.0),
+                    top: Val::Px(-200.0),
+                    ..Default::default()
+                },
+                justify_content: JustifyContent::Center,
+                ..Default::default()
+            },
+            material: assets.transparent.clone(),
+            ..Default::default()
+        })
+        .insert(ResultBanner {
+            timer: Timer::from_seconds(BANNER_DELAY + BANNER_SLIDE, false),
+        })
+        .with_children(|parent| {
+            parent
+                .spawn_bundle(NodeBundle {
+                    style: Style {
+                        padding: Rect::all(Val::Px(20.0)),
+                        ..Default::default()
+                    },
+                    material: assets.panel.clone(),
+                    ..Default::default()
+                })
+                .with_children(|parent| {
+                    parent.spawn_bundle(TextBundle {
+                        text: assets.text(&tr_with(settings.language, "wins", winner), 60.0),
+                        ..Default::default()
+                    });
+                });
+        });
+}
+
+/// Slide the result banner in, and remove it when a new position is loaded
+fn slide_result_banner(
+    mut commands: Commands,
+    time: Res<Time>,
+    settings: Res<Settings>,
+    mut load_position_events: EventReader<LoadPositionEvent>,
+    mut query: Query<(Entity, &mut ResultBanner, &mut Style)>,
+) {
+    let loaded = load_position_events.iter().last().is_some();
+    for (entity, mut banner, mut style) in query.iter_mut() {
+        if loaded {
+            commands.entity(entity).despawn_recursive();
+            continue;
+        }
+        if banner.timer.finished() {
+            continue;
+        }
+
+        banner.timer.tick(time.delta());
+        let elapsed = banner.timer.elapsed_secs() - BANNER_DELAY;
+        let t = if settings.reduced_motion && elapsed >= 0.0 {
+            1.0
+        } else {
+            (elapsed / BANNER_SLIDE).max(0.0).min(1.0)
+        };
+        // Decelerate into place
+        let t = 1.0 - (1.0 - t).powi(3);
+        style.position.top = Val::Px(-200.0 + t * 320.0);
+    }
+}
+
+/// Loading screen root
+struct LoadingScreen;
+
+/// Filled part of the loading bar
+struct LoadingBar;
+
+/// Text of the loading screen, telling when loading failed
+struct LoadingText;
+
+/// Show a loading screen while the assets are being loaded
+fn init_loading_screen(mut commands: Commands, assets: Res<UiAssets>, settings: Res<Settings>) {
+    commands
+        .spawn_bundle(NodeBundle {
+            style: Style {
+                size: Size::new(Val::Percent(100.0), Val::Percent(100.0)),
+                position_type: PositionType::Absolute,
+                flex_direction: FlexDirection::ColumnReverse,
+                justify_content: JustifyContent::Center,
+                align_items: AlignItems::Center,
+                ..Default::default()
+            },
+            material: assets.panel.clone(),
+            ..Default::default()
+        })
+        .insert(LoadingScreen)
+        .with_children(|parent| {
+            parent
+                .spawn_bundle(TextBundle {
+                    text: assets.text(tr(settings.language, "loading"), 40.0),
+                    ..Default::default()
+                })
+                .insert(Localized("loading"))
+                .insert(LoadingText);
+            parent
+                .spawn_bundle(NodeBundle {
+                    style: Style {
+                        size: Size::new(Val::Px(300.0), Val::Px(20.0)),
+                        margin: Rect::all(Val::Px(10.0)),
+                        ..Default::default()
+                    },
+                    material: assets.button.clone(),
+                    ..Default::default()
+                })
+                .with_children(|parent| {
+                    parent
+                        .spawn_bundle(NodeBundle {
+                            style: Style {
+                                size: Size::new(Val::Percent(0.0), Val::Percent(100.0)),
+                                ..Default::default()
+                            },
+                            material: assets.progress.clone(),
+                            ..Default::default()
+                        })
+                        .insert(LoadingBar);
+                });
+        });
+}
+
+/// Fill the loading bar with the loading progress, or tell that loading failed
+fn loading_bar_update(
+    progress: Res<LoadingProgress>,
+    settings: Res<Settings>,
+    mut query: Query<&mut Style, With<LoadingBar>>,
+    mut text_query: Query<(&mut Text, &mut Localized), With<LoadingText>>,
+) {
+    if !progress.is_changed() {
+        return;
+    }
+    for mut style in query.iter_mut() {
+        style.size.width = Val::Percent(progress.fraction() * 100.0);
+    }
+    if progress.failed {
+        for (mut text, mut localized) in text_query.iter_mut() {
+            localized.0 = "loading_failed";
+            text.sections[0].value = tr(settings.language, "loading_failed").to_string();
+        }
+    }
+}
+
+/// Remove the loading screen
+fn close_loading_screen(mut commands: Commands, query: Query<Entity, With<LoadingScreen>>) {
+    for entity in query.iter() {
+        commands.entity(entity).despawn_recursive();
+    }
 }
 
 pub struct UiPlugin;
 
 impl Plugin for UiPlugin {
     fn build(&self, app: &mut AppBuilder) {
-        app.add_startup_system(init_next_move_text.system())
-            .add_system(next_move_text_update.system());
+        app.init_resource::<UiAssets>()
+            .add_startup_system(init_layout.system())
+            .add_system_set(
+                SystemSet::on_enter(AppState::Loading).with_system(init_loading_screen.system()),
+            )
+            .add_system_set(
+                SystemSet::on_update(AppState::Loading).with_system(loading_bar_update.system()),
+            )
+            .add_system_set(
+                SystemSet::on_exit(AppState::Loading).with_system(close_loading_screen.system()),
+            )
+            .add_system(next_move_text_update.system())
+            .add_system(clock_text_update.system())
+            .add_system(clock_hand_update.system())
+            .add_system(place_clocks.system())
+            .add_system(turn_bar_update.system())
+            .add_system(history_text_update.system())
+            .add_system(collapse_history_click.system())
+            .add_system(resize_history.system())
+            .add_system(history_panel_update.system())
+            .add_system(captured_text_update.system())
+            .add_system(hovered_square_text_update.system())
+            .add_system(clock_state_effects.system())
+            .add_system(pulse_clock_border.system())
+            .add_system(button_hover.system())
+            .add_system(confirm_button_update.system())
+            .add_system(confirm_button_click.system())
+            .add_system(show_result_banner.system())
+            .add_system(slide_result_banner.system())
+            .add_system(nag_button_click.system())
+            .add_system(copy_button_click.system());
     }
 }
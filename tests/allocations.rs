@@ -0,0 +1,69 @@
+//! Selecting and validating moves must not allocate once the board state is filled.
+//! Kept in its own test binary, as it counts the allocations of the whole process.
+use bevy_chess::fen::{parse_fen, STARTING_FEN};
+use bevy_chess::pieces::{is_attacked, is_hanging, BoardState};
+use bevy_chess::{HighlightLayer, Highlights};
+
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+/// System allocator counting allocations.
+struct CountingAllocator;
+
+static ALLOCATIONS: AtomicUsize = AtomicUsize::new(0);
+
+unsafe impl GlobalAlloc for CountingAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Ordering::SeqCst);
+        System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout)
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Ordering::SeqCst);
+        System.realloc(ptr, layout, new_size)
+    }
+}
+
+#[global_allocator]
+static ALLOCATOR: CountingAllocator = CountingAllocator;
+
+/// Number of allocations made while running `f`.
+fn allocations(f: impl FnOnce()) -> usize {
+    let before = ALLOCATIONS.load(Ordering::SeqCst);
+    f();
+    ALLOCATIONS.load(Ordering::SeqCst) - before
+}
+
+// A single test, so that no other test allocates while counting
+#[test]
+fn move_validation_does_not_allocate() {
+    let start = parse_fen(STARTING_FEN).unwrap().pieces;
+    let middlegame = parse_fen("r1bqk2r/pppp1ppp/2n2n2/2b1p3/2B1P3/2N2N2/PPPP1PPP/R1BQK2R w")
+        .unwrap()
+        .pieces;
+    let mut board = BoardState::default();
+    let mut highlights = Highlights::default();
+    board.refill(start.iter().copied());
+    highlights.set(HighlightLayer::LegalMoves, (0..64).map(|i| (i / 8, i % 8)));
+
+    let count = allocations(|| {
+        board.refill(middlegame.iter().copied());
+        let pieces = &board.pieces;
+        for piece in pieces {
+            highlights.set(
+                HighlightLayer::LegalMoves,
+                (0..8)
+                    .flat_map(|x| (0..8).map(move |y| (x, y)))
+                    .filter(|&to| piece.is_move_valid(to, pieces)),
+            );
+            is_attacked((piece.x, piece.y), piece.color.opponent(), pieces);
+            is_hanging(piece, pieces);
+        }
+        board.refill(start.iter().copied());
+    });
+    assert_eq!(count, 0);
+}